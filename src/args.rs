@@ -1,54 +1,368 @@
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
 
+use crate::Result;
+use crate::batch;
+use crate::chunk::{Chunk, CrcAlgorithm, decode_hex, inflate};
+use crate::chunk_type::ChunkType;
+
+/// Standard chunk types that `encode` refuses to write unless forced
+pub const PROTECTED_CHUNK_TYPES: [&str; 14] = [
+    "IHDR", "PLTE", "IDAT", "IEND", "cHRM", "gAMA", "sBIT", "sRGB", "bKGD", "hIST", "tRNS", "pHYs",
+    "sPLT", "tIME",
+];
+
+/// Private ancillary chunk type `encode --tag` stores its note under unless
+/// `--tag-type` says otherwise
+pub const DEFAULT_TAG_CHUNK_TYPE: &str = "pnMe";
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "pngme", about = "PNGMe CLI Tool")]
 pub enum PngMeArgs {
-    Encode(EncodeArgs),
+    Encode(Box<EncodeArgs>),
     Decode(DecodeArgs),
     Print(PrintArgs),
     Remove(RemoveArgs),
+    Search(SearchArgs),
+    Retype(RetypeArgs),
+    Repair(RepairArgs),
+    Info(InfoArgs),
+    /// Make a PNG safe to share: keeps the critical chunks, the rendering
+    /// chunks (cHRM, gAMA, iCCP, sBIT, sRGB, bKGD, tRNS, pHYs, acTL, fcTL,
+    /// fdAT) and other public safe-to-copy chunks. Drops private chunks,
+    /// unsafe-to-copy chunks and text/EXIF metadata (tEXt, iTXt, zTXt, eXIf).
+    Sanitize(SanitizeArgs),
+    Dump(DumpArgs),
+    Import(ImportArgs),
+    Edit(EditArgs),
+    Find(FindArgs),
+    Verify(VerifyArgs),
+    Diff(DiffArgs),
+    Rewrite(RewriteArgs),
+    Batch(BatchArgs),
+    DumpImage(DumpImageArgs),
+    PixelHash(PixelHashArgs),
 }
 
 #[derive(Debug, StructOpt)]
 pub struct EncodeArgs {
     /// Input Png file path
-    #[structopt(short, long)]
-    pub input: PathBuf,
-    
+    #[structopt(
+        short,
+        long,
+        required_unless = "input-base64",
+        conflicts_with = "input-base64"
+    )]
+    pub input: Option<PathBuf>,
+
+    /// Read the input PNG from a base64 string instead of a file
+    #[structopt(long = "input-base64")]
+    pub input_base64: Option<String>,
+
     /// Chunk type to encode
-    #[structopt(short = "c", long = "chunk-type")]
-    pub chunk_type: String,
-    
+    #[structopt(
+        short = "c",
+        long = "chunk-type",
+        required_unless_one = &["auto-type", "chunk-type-file", "pair"],
+        conflicts_with_all = &["auto-type", "chunk-type-file", "pair"]
+    )]
+    pub chunk_type: Option<String>,
+
+    /// Read the chunk type from a file holding exactly 4 bytes
+    #[structopt(long = "chunk-type-file", conflicts_with_all = &["auto-type", "pair"])]
+    pub chunk_type_file: Option<PathBuf>,
+
+    /// Pick an unused private ancillary chunk type automatically
+    #[structopt(long = "auto-type", conflicts_with = "pair")]
+    pub auto_type: bool,
+
+    /// Chunk type and secret message in one, as TYPE=MESSAGE (e.g. ruSt=hello)
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub pair: Option<(ChunkType, String)>,
+
     /// Secret message to encode
-    #[structopt(short)]
-    pub secret: String,
-    
+    #[structopt(
+        short,
+        required_unless_one = &["hex-secret", "pair", "multi-message"],
+        conflicts_with_all = &["hex-secret", "pair", "multi-message"]
+    )]
+    pub secret: Option<String>,
+
+    /// Secret message as a hex string of binary data (e.g. "deadbeef")
+    #[structopt(long = "hex-secret", conflicts_with_all = &["pair", "multi-message"])]
+    pub hex_secret: Option<String>,
+
+    /// Store several messages in one chunk with LEB128 length prefixes; may be
+    /// repeated, decode with --multi-message
+    #[structopt(long = "multi-message", number_of_values = 1, conflicts_with = "pair")]
+    pub multi_message: Vec<String>,
+
     /// Output file path
     #[structopt(short, long)]
     pub output: Option<PathBuf>,
-        
-    /// index of the chunk to insert the secret message
-    #[structopt(long = "index")]
-    pub index: Option<usize>
+
+    /// Print the resulting PNG as base64 instead of writing a file
+    #[structopt(long = "output-base64", conflicts_with = "output")]
+    pub output_base64: bool,
+
+    /// index of the chunk to insert the secret message, negative counts from
+    /// the end; defaults to right before IEND
+    #[structopt(long = "index", allow_hyphen_values = true)]
+    pub index: Option<isize>,
+
+    /// Insert the secret right before the first chunk of this type (e.g. IDAT)
+    #[structopt(long = "append-before", conflicts_with = "index")]
+    pub append_before: Option<String>,
+
+    /// Allow encoding into a standard PNG chunk type (may corrupt the image)
+    #[structopt(long = "force-critical")]
+    pub force_critical: bool,
+
+    /// Pad the secret message with zero bytes up to this length
+    #[structopt(long = "pad-to-bytes", conflicts_with = "framed")]
+    pub pad_to_bytes: Option<usize>,
+
+    /// Store the message as a length-prefixed record, appending to an existing chunk of the same type
+    #[structopt(long)]
+    pub framed: bool,
+
+    /// Prefix the message with a CRC32 checksum that decode --integrity verifies
+    #[structopt(long)]
+    pub integrity: bool,
+
+    /// Write bytes found after IEND in the input back after IEND in the output
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+
+    /// Zlib-compress the message before storing it; decode with --compressed
+    #[structopt(long)]
+    pub compress: bool,
+
+    /// Deflate level for --compress, from 0 (store) to 9 (smallest)
+    #[structopt(long = "compress-level", default_value = "6", parse(try_from_str = parse_compress_level))]
+    pub compress_level: u32,
+
+    /// CRC-32 variant to write every chunk checksum with: iso-hdlc (PNG spec),
+    /// bzip2 or iscsi. Anything but iso-hdlc produces files other decoders reject.
+    #[structopt(long = "crc-algo", default_value = "iso-hdlc")]
+    pub crc_algo: CrcAlgorithm,
+
+    /// Re-read the saved file and check it matches what was written
+    #[structopt(long = "verify-after-write")]
+    pub verify_after_write: bool,
+
+    /// Encrypt the message to this X25519 public key (32 raw bytes or 64 hex
+    /// digits); decode with --private-key. Needs the `crypto` feature.
+    #[structopt(long = "recipient-pubkey", conflicts_with_all = &["framed", "integrity"])]
+    pub recipient_pubkey: Option<PathBuf>,
+
+    /// Also store this provenance note (e.g. who encoded the file and when)
+    /// in its own chunk
+    #[structopt(long)]
+    pub tag: Option<String>,
+
+    /// Chunk type for --tag, to avoid colliding with types reserved elsewhere
+    /// [default: pnMe]
+    #[structopt(long = "tag-type", requires = "tag")]
+    pub tag_type: Option<ChunkType>,
+}
+
+impl EncodeArgs {
+    /// The chunk type given by `-c` or `--chunk-type-file`, `None` with `--auto-type`
+    pub fn requested_chunk_type(&self) -> Result<Option<ChunkType>> {
+        if self.auto_type {
+            return Ok(None);
+        }
+
+        match (&self.chunk_type, &self.chunk_type_file, &self.pair) {
+            (Some(chunk_type), _, _) => Ok(Some(ChunkType::from_str(chunk_type)?)),
+            (None, Some(path), _) => Ok(Some(read_chunk_type_file(path)?)),
+            (None, None, Some((chunk_type, _))) => Ok(Some(chunk_type.clone())),
+            (None, None, None) => Err("No chunk type given".into()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let requested = self.requested_chunk_type()?;
+        let tag_type = self.tag_chunk().map(|tag| tag.chunk_type().clone());
+        for chunk_type in requested.iter().chain(tag_type.iter()) {
+            check_protected_type(chunk_type, self.force_critical)?;
+        }
+
+        Ok(())
+    }
+
+    /// The chunk holding the --tag note, if one was given
+    pub fn tag_chunk(&self) -> Option<Chunk> {
+        let tag = self.tag.as_ref()?;
+        let chunk_type = self
+            .tag_type
+            .clone()
+            .unwrap_or_else(|| ChunkType::from_str(DEFAULT_TAG_CHUNK_TYPE).unwrap());
+        Some(Chunk::new(chunk_type, tag.as_bytes().to_vec()))
+    }
 }
 
 #[derive(Debug, StructOpt)]
-pub struct  DecodeArgs {
-    /// Input Png file path
+pub struct DecodeArgs {
+    /// Input Png file path, or an http:// URL to download it from
     #[structopt(short, long)]
     pub input: PathBuf,
+
+    /// Give up downloading a URL input after this many seconds
+    #[structopt(long, default_value = "30")]
+    pub timeout: u64,
+
+    /// Abort downloading a URL input once it exceeds this many bytes
+    #[structopt(long = "max-file-size", default_value = "67108864")]
+    pub max_file_size: u64,
     
     /// Chunk type to encode
     #[structopt(short = "c", long = "chunk-type")]
     pub chunk_type: String,
+
+    /// Split the chunk data into its length-prefixed records
+    #[structopt(long)]
+    pub framed: bool,
+
+    /// Only decode the bytes in START:END of the chunk data (END exclusive)
+    #[structopt(long, parse(try_from_str = parse_range), conflicts_with = "framed")]
+    pub range: Option<(usize, usize)>,
+
+    /// Verify and strip the checksum written by encode --integrity
+    #[structopt(long, conflicts_with_all = &["framed", "range"])]
+    pub integrity: bool,
+
+    /// Decode the nth (1-based) chunk of the type instead of the first
+    #[structopt(long)]
+    pub nth: Option<usize>,
+
+    /// Print the message line by line with line numbers
+    #[structopt(long = "show-lines", conflicts_with_all = &["framed", "range", "integrity"])]
+    pub show_lines: bool,
+
+    /// Also print a CRC32 of each decoded message to compare out-of-band
+    #[structopt(long = "show-hash")]
+    pub show_hash: bool,
+
+    /// Inflate data written by encode --compress; with --integrity the
+    /// checksum is verified first, then the payload is inflated
+    #[structopt(long, conflicts_with_all = &["framed", "range", "show-lines"])]
+    pub compressed: bool,
+
+    /// Split the data into the messages written by encode --multi-message
+    #[structopt(
+        long = "multi-message",
+        conflicts_with_all = &["framed", "range", "integrity", "show-lines", "compressed"]
+    )]
+    pub multi_message: bool,
+
+    /// Decrypt data written by encode --recipient-pubkey with this X25519
+    /// private key. Needs the `crypto` feature.
+    #[structopt(
+        long = "private-key",
+        conflicts_with_all = &["framed", "range", "integrity", "multi-message"]
+    )]
+    pub private_key: Option<PathBuf>,
+
+    /// Fail if the file has a critical chunk type the PNG spec doesn't define
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// CRC-32 variant the input's chunk checksums were written with
+    /// (see encode --crc-algo)
+    #[structopt(long = "crc-algo", default_value = "iso-hdlc")]
+    pub crc_algo: CrcAlgorithm,
+
+    /// Match the chunk type ignoring letter case. Case carries the
+    /// critical/public/reserved/safe-to-copy bits, so this may pick a
+    /// different kind of chunk than the one typed.
+    #[structopt(long = "ignore-case")]
+    pub ignore_case: bool,
+
+    /// Print only the byte length of the decoded message
+    #[structopt(long = "size-only", conflicts_with_all = &["framed", "multi-message", "show-lines"])]
+    pub size_only: bool,
+}
+
+impl DecodeArgs {
+    /// The single message held by `chunk` after undoing --integrity or
+    /// --range and then --compressed, the reverse of the order encode applies
+    /// them in; not for the multi-message modes
+    pub fn decoded_payload<'a>(&self, chunk: &'a Chunk) -> Result<Cow<'a, [u8]>> {
+        let data = if self.integrity {
+            chunk.integrity_payload()?.0
+        } else {
+            match self.range {
+                Some((start, end)) => chunk.data_range(start, end)?,
+                None => chunk.data(),
+            }
+        };
+        if self.compressed {
+            let inflated =
+                inflate(data).map_err(|e| format!("Failed to inflate chunk data: {}", e))?;
+            return Ok(Cow::Owned(inflated));
+        }
+        Ok(Cow::Borrowed(data))
+    }
 }
 
 #[derive(Debug, StructOpt)]
 pub struct PrintArgs {
     /// Input Png file path
     #[structopt(short, long)]
-    pub input: PathBuf   
+    pub input: PathBuf,
+
+    /// Only list chunks with this type property: critical, public, reserved or
+    /// safe-to-copy, prefixed with '!' to negate. Repeat to combine.
+    #[structopt(long = "where", number_of_values = 1)]
+    pub filters: Vec<TypeFilter>,
+
+    /// Print one summary line per chunk
+    #[structopt(long)]
+    pub compact: bool,
+}
+
+/// A `--where` clause over one of the `ChunkType` property bits
+#[derive(Debug, Clone, Copy)]
+pub struct TypeFilter {
+    property: fn(&ChunkType) -> bool,
+    negated: bool,
+}
+
+impl TypeFilter {
+    pub fn matches(&self, chunk_type: &ChunkType) -> bool {
+        (self.property)(chunk_type) != self.negated
+    }
+}
+
+impl FromStr for TypeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (negated, name) = match s.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, s),
+        };
+
+        let property: fn(&ChunkType) -> bool = match name {
+            "critical" => ChunkType::is_critical,
+            "public" => ChunkType::is_public,
+            "reserved" => ChunkType::is_reserved_bit_valid,
+            "safe-to-copy" => ChunkType::is_safe_to_copy,
+            _ => {
+                return Err(format!(
+                    "Unknown property '{}', expected critical, public, reserved or safe-to-copy",
+                    name
+                ));
+            }
+        };
+
+        Ok(TypeFilter { property, negated })
+    }
 }
 
 #[derive(Debug, StructOpt)]
@@ -60,4 +374,601 @@ pub struct RemoveArgs {
     /// Chunk type to remove
     #[structopt(short = "c", long = "chunk-type")]
     pub chunk_type: String,
+
+    /// Report how many bytes the removal frees
+    #[structopt(long = "show-savings")]
+    pub show_savings: bool,
+
+    /// Report what would be removed without writing the file; combine with
+    /// --show-savings to preview the bytes freed
+    #[structopt(long = "dry-run", conflicts_with = "verify-after-write")]
+    pub dry_run: bool,
+
+    /// Re-read the saved file and check it matches what was written
+    #[structopt(long = "verify-after-write")]
+    pub verify_after_write: bool,
+
+    /// Match the chunk type ignoring letter case. Case carries the
+    /// critical/public/reserved/safe-to-copy bits, so this may pick a
+    /// different kind of chunk than the one typed.
+    #[structopt(long = "ignore-case")]
+    pub ignore_case: bool,
+
+    /// Write bytes found after IEND in the input back after IEND in the output
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SearchArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Pattern to search for in chunk data
+    #[structopt(short, long)]
+    pub pattern: String,
+
+    /// Treat the pattern as a hex string (e.g. "deadbeef")
+    #[structopt(long)]
+    pub hex: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RetypeArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Index of the chunk to downgrade to ancillary
+    #[structopt(long = "index")]
+    pub index: usize,
+
+    /// Write bytes found after IEND in the input back after IEND in the output
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RepairArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Output file path, defaults to overwriting the input
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Remove every chunk and byte after the first IEND
+    #[structopt(long = "strip-after-iend")]
+    pub strip_after_iend: bool,
+
+    /// Merge all chunks of this ancillary type into one; may be repeated
+    #[structopt(long, number_of_values = 1)]
+    pub flatten: Vec<String>,
+
+    /// Write bytes found after IEND back after IEND
+    #[structopt(long = "keep-trailing-data", conflicts_with = "strip-after-iend")]
+    pub keep_trailing_data: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct InfoArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Show how many bytes could be hidden with 1 to 4 LSB encoding
+    #[structopt(long)]
+    pub capacity: bool,
+
+    /// Fail if the file has a critical chunk type the PNG spec doesn't define
+    #[structopt(long)]
+    pub strict: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SanitizeArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Output file path, defaults to overwriting the input
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Write bytes found after IEND in the input back after IEND in the output
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DumpArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Write the dump to this file instead of stdout
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DumpImageArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// File to write the concatenated IDAT data to
+    #[structopt(short, long)]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct PixelHashArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ImportArgs {
+    /// Dump file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Output Png file path
+    #[structopt(short, long)]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct EditArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Chunk type whose data to edit
+    #[structopt(short = "c", long = "chunk-type")]
+    pub chunk_type: String,
+
+    /// Replace the data with this message instead of opening $EDITOR
+    #[structopt(short, long, conflicts_with = "secret-file")]
+    pub secret: Option<String>,
+
+    /// Replace the data with the contents of this file instead of opening $EDITOR
+    #[structopt(long = "secret-file")]
+    pub secret_file: Option<PathBuf>,
+
+    /// Output file path, defaults to overwriting the input
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Re-read the saved file and check it matches what was written
+    #[structopt(long = "verify-after-write")]
+    pub verify_after_write: bool,
+
+    /// Write bytes found after IEND in the input back after IEND in the output
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FindArgs {
+    /// Directory to search for .png files
+    #[structopt(short, long)]
+    pub dir: PathBuf,
+
+    /// Chunk type to look for
+    #[structopt(short = "c", long = "chunk-type")]
+    pub chunk_type: String,
+
+    /// Also search subdirectories
+    #[structopt(short, long)]
+    pub recursive: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct VerifyArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DiffArgs {
+    /// First Png file path
+    #[structopt(short = "a", long)]
+    pub first: PathBuf,
+
+    /// Second Png file path
+    #[structopt(short = "b", long)]
+    pub second: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RewriteArgs {
+    /// Input Png file path
+    #[structopt(short, long)]
+    pub input: PathBuf,
+
+    /// Output file path, defaults to overwriting the input
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Fail if the file has a critical chunk type the PNG spec doesn't define
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Accept bad CRCs on load and write every CRC back corrected
+    #[structopt(long)]
+    pub recrc: bool,
+
+    /// Drop any bytes after IEND instead of writing them back
+    #[structopt(long = "strip-trailing")]
+    pub strip_trailing: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct BatchArgs {
+    /// Print how many files succeeded and failed at the end. Implies
+    /// --on-error continue unless that is given explicitly.
+    #[structopt(long)]
+    pub summary: bool,
+
+    /// What to do when a file fails: stop (abort with its error) or continue
+    /// (log it, process the rest and exit non-zero at the end)
+    #[structopt(long = "on-error")]
+    pub on_error: Option<batch::OnError>,
+
+    /// Write bytes found after IEND in each file back after IEND
+    #[structopt(long = "keep-trailing-data")]
+    pub keep_trailing_data: bool,
+
+    #[structopt(subcommand)]
+    pub op: BatchOp,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum BatchOp {
+    /// Encode the same message into every file, in place
+    Encode {
+        /// Chunk type to encode
+        #[structopt(short = "c", long = "chunk-type")]
+        chunk_type: String,
+
+        /// Secret message to encode
+        #[structopt(short)]
+        secret: String,
+
+        /// Allow encoding into a standard PNG chunk type (may corrupt the images)
+        #[structopt(long = "force-critical")]
+        force_critical: bool,
+
+        /// Png files to modify
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Remove the first chunk of a type from every file, in place
+    Remove {
+        /// Chunk type to remove
+        #[structopt(short = "c", long = "chunk-type")]
+        chunk_type: String,
+
+        /// Png files to modify
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+impl BatchArgs {
+    /// The --on-error policy, defaulting to continue with --summary and stop otherwise
+    pub fn on_error(&self) -> batch::OnError {
+        match (self.on_error, self.summary) {
+            (Some(on_error), _) => on_error,
+            (None, true) => batch::OnError::Continue,
+            (None, false) => batch::OnError::Stop,
+        }
+    }
+}
+
+impl BatchOp {
+    pub fn files(&self) -> &[PathBuf] {
+        match self {
+            BatchOp::Encode { files, .. } | BatchOp::Remove { files, .. } => files,
+        }
+    }
+
+    /// Applies the same chunk type checks as the single-file commands, once
+    /// before any file is touched
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            BatchOp::Encode {
+                chunk_type,
+                force_critical,
+                ..
+            } => check_protected_type(&ChunkType::from_str(chunk_type)?, *force_critical),
+            BatchOp::Remove { .. } => Ok(()),
+        }
+    }
+
+    /// Runs the operation on a single file
+    pub fn apply(&self, path: &Path, keep_trailing_data: bool) -> Result<()> {
+        match self {
+            BatchOp::Encode {
+                chunk_type, secret, ..
+            } => batch::encode_into_file(path, chunk_type, secret, keep_trailing_data),
+            BatchOp::Remove { chunk_type, .. } => {
+                batch::remove_from_file(path, chunk_type, keep_trailing_data)
+            }
+        }
+    }
+}
+
+impl EditArgs {
+    /// The new data given by `--secret` or `--secret-file`, `None` when the
+    /// edit should happen interactively
+    pub fn replacement(&self) -> Result<Option<Vec<u8>>> {
+        match (&self.secret, &self.secret_file) {
+            (Some(secret), _) => Ok(Some(secret.as_bytes().to_vec())),
+            (None, Some(path)) => Ok(Some(fs::read(path)?)),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl SearchArgs {
+    pub fn needle(&self) -> Result<Vec<u8>> {
+        if self.hex {
+            decode_hex(&self.pattern)
+        } else {
+            Ok(self.pattern.as_bytes().to_vec())
+        }
+    }
+}
+
+/// Refuses the standard types in `PROTECTED_CHUNK_TYPES` unless `force_critical` is set
+fn check_protected_type(chunk_type: &ChunkType, force_critical: bool) -> Result<()> {
+    if !force_critical && PROTECTED_CHUNK_TYPES.contains(&chunk_type.to_string().as_str()) {
+        return Err(format!(
+            "Refusing to encode into standard chunk type '{}', pass --force-critical to override",
+            chunk_type
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn read_chunk_type_file(path: &Path) -> Result<ChunkType> {
+    let bytes = fs::read(path)?;
+    let bytes: [u8; 4] = bytes.as_slice().try_into().map_err(|_| {
+        format!(
+            "Chunk type file must hold exactly 4 bytes, got {}",
+            bytes.len()
+        )
+    })?;
+    Ok(ChunkType::try_from(bytes)?)
+}
+
+/// Splits `TYPE=MESSAGE` on the first `=`, so the message may contain `=`
+fn parse_pair(s: &str) -> Result<(ChunkType, String)> {
+    let (chunk_type, message) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid pair '{}', expected TYPE=MESSAGE", s))?;
+    Ok((ChunkType::from_str(chunk_type)?, message.to_string()))
+}
+
+fn parse_compress_level(s: &str) -> Result<u32> {
+    match s.parse()? {
+        level @ 0..=9 => Ok(level),
+        level => Err(format!("Compression level must be between 0 and 9, got {}", level).into()),
+    }
+}
+
+fn parse_range(s: &str) -> Result<(usize, usize)> {
+    let (start, end) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid range '{}', expected START:END", s))?;
+    Ok((start.parse()?, end.parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::deflate;
+
+    fn matching(filters: &[&str], types: &[&str]) -> Vec<String> {
+        let filters: Vec<TypeFilter> = filters.iter().map(|f| f.parse().unwrap()).collect();
+        types
+            .iter()
+            .map(|t| ChunkType::from_str(t).unwrap())
+            .filter(|t| filters.iter().all(|f| f.matches(t)))
+            .map(|t| t.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_where_not_critical() {
+        let types = ["IHDR", "gAMA", "ruSt", "IDAT", "tEXt", "IEND"];
+        assert_eq!(
+            matching(&["!critical"], &types),
+            vec!["gAMA", "ruSt", "tEXt"]
+        );
+    }
+
+    #[test]
+    fn test_where_combined() {
+        let types = ["IHDR", "gAMA", "ruSt", "tEXt"];
+        assert_eq!(
+            matching(&["!critical", "safe-to-copy"], &types),
+            vec!["ruSt", "tEXt"]
+        );
+        assert_eq!(
+            matching(&["public", "!safe-to-copy"], &types),
+            vec!["IHDR", "gAMA"]
+        );
+    }
+
+    #[test]
+    fn test_read_chunk_type_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk_type_ok");
+        fs::write(&path, b"ruSt").unwrap();
+        let chunk_type = read_chunk_type_file(&path);
+
+        assert_eq!(chunk_type.unwrap().to_string(), "ruSt");
+    }
+
+    #[test]
+    fn test_read_chunk_type_file_wrong_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk_type_long");
+        fs::write(&path, b"ruSt\n").unwrap();
+        let chunk_type = read_chunk_type_file(&path);
+
+        assert!(chunk_type.is_err());
+    }
+
+    #[test]
+    fn test_where_unknown_property() {
+        assert!("!bogus".parse::<TypeFilter>().is_err());
+    }
+
+    #[test]
+    fn test_parse_pair() {
+        let (chunk_type, message) = parse_pair("ruSt=a=b").unwrap();
+        assert_eq!(chunk_type.to_string(), "ruSt");
+        assert_eq!(message, "a=b");
+
+        assert!(parse_pair("ruSt").is_err());
+        assert!(parse_pair("ru5t=hello").is_err());
+    }
+
+    #[test]
+    fn test_decoded_payload_size() {
+        let secret = "This is where your secret message will be!";
+        let chunk = Chunk::from_type_and_str("ruSt", secret).unwrap();
+        let args = DecodeArgs::from_iter(&["decode", "-i", "in.png", "-c", "ruSt", "--size-only"]);
+        assert_eq!(args.decoded_payload(&chunk).unwrap().len(), secret.len());
+
+        let compressed = Chunk::new(
+            chunk.chunk_type().clone(),
+            deflate(secret.as_bytes(), 9).unwrap(),
+        );
+        let args = DecodeArgs::from_iter(&[
+            "decode",
+            "-i",
+            "in.png",
+            "-c",
+            "ruSt",
+            "--size-only",
+            "--compressed",
+        ]);
+        assert_eq!(
+            args.decoded_payload(&compressed).unwrap().len(),
+            secret.len()
+        );
+
+        let args = DecodeArgs::from_iter(&[
+            "decode",
+            "-i",
+            "in.png",
+            "-c",
+            "ruSt",
+            "--size-only",
+            "--range",
+            "2:10",
+        ]);
+        assert_eq!(args.decoded_payload(&chunk).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_compress_with_integrity_round_trip() {
+        let secret = "This is where your secret message will be!";
+        // encode --compress --integrity compresses first, then adds the checksum
+        let encode = EncodeArgs::from_iter(&[
+            "encode",
+            "-i",
+            "in.png",
+            "-c",
+            "ruSt",
+            "-s",
+            secret,
+            "--compress",
+            "--integrity",
+        ]);
+        let compressed = deflate(secret.as_bytes(), encode.compress_level).unwrap();
+        let chunk =
+            Chunk::from_type_and_bytes("ruSt", &Chunk::add_integrity_header(&compressed)).unwrap();
+
+        let decode = DecodeArgs::from_iter(&[
+            "decode",
+            "-i",
+            "in.png",
+            "-c",
+            "ruSt",
+            "--compressed",
+            "--integrity",
+        ]);
+        assert!(chunk.integrity_payload().unwrap().1);
+        assert_eq!(
+            decode.decoded_payload(&chunk).unwrap().as_ref(),
+            secret.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_tag_type() {
+        let base = [
+            "encode", "-i", "in.png", "-c", "ruSt", "-s", "hi", "--tag", "by alice",
+        ];
+        let args = EncodeArgs::from_iter(base);
+        let tag = args.tag_chunk().unwrap();
+        assert_eq!(tag.chunk_type().to_string(), DEFAULT_TAG_CHUNK_TYPE);
+        assert_eq!(tag.data(), b"by alice");
+
+        let args = EncodeArgs::from_iter(base.iter().chain(&["--tag-type", "tgXy"]));
+        assert_eq!(args.tag_chunk().unwrap().chunk_type().to_string(), "tgXy");
+        assert!(args.validate().is_ok());
+
+        let args = EncodeArgs::from_iter(base.iter().chain(&["--tag-type", "tIME"]));
+        assert!(args.validate().is_err());
+        assert!(EncodeArgs::from_iter_safe(base.iter().chain(&["--tag-type", "t3xt"])).is_err());
+        assert!(
+            EncodeArgs::from_iter(["encode", "-i", "in.png", "-c", "ruSt", "-s", "hi"])
+                .tag_chunk()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_batch_encode_refuses_protected_type() {
+        let args = BatchArgs::from_iter(["batch", "encode", "-c", "IDAT", "-s", "x", "a.png"]);
+        assert!(args.op.validate().is_err());
+
+        let args = BatchArgs::from_iter([
+            "batch",
+            "encode",
+            "-c",
+            "IDAT",
+            "-s",
+            "x",
+            "--force-critical",
+            "a.png",
+        ]);
+        assert!(args.op.validate().is_ok());
+        let args = BatchArgs::from_iter(["batch", "remove", "-c", "IDAT", "a.png"]);
+        assert!(args.op.validate().is_ok());
+    }
+
+    #[test]
+    fn test_pad_to_bytes_conflicts_with_framed() {
+        let base = ["encode", "-i", "in.png", "-c", "ruSt", "-s", "hi"];
+        assert!(EncodeArgs::from_iter_safe(base.iter().chain(&["--pad-to-bytes", "16"])).is_ok());
+        assert!(EncodeArgs::from_iter_safe(base.iter().chain(&["--framed"])).is_ok());
+        assert!(
+            EncodeArgs::from_iter_safe(base.iter().chain(&["--framed", "--pad-to-bytes", "16"]))
+                .is_err()
+        );
+    }
 }
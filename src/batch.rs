@@ -0,0 +1,188 @@
+#![allow(unused_imports, dead_code)]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::Result;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// What a batch run does when an operation on one file fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Abort with that file's error
+    Stop,
+    /// Record the failure and move on to the next file
+    Continue,
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stop" => Ok(OnError::Stop),
+            "continue" => Ok(OnError::Continue),
+            other => Err(format!(
+                "Unknown error policy '{}', expected stop or continue",
+                other
+            )),
+        }
+    }
+}
+
+/// Per-file outcome of a batch run
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub succeeded: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchReport {
+    pub fn has_failures(&self) -> bool {
+        !self.failed.is_empty()
+    }
+
+    /// The aggregate tally printed by `--summary`
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} succeeded, {} failed",
+            self.succeeded.len(),
+            self.failed.len()
+        );
+        for (path, error) in &self.failed {
+            summary.push_str(&format!("\n  {}: {}", path.display(), error));
+        }
+        summary
+    }
+}
+
+/// Runs `op` on every file, recording each result. With `OnError::Stop`
+/// the first failure is returned as an error naming the file instead.
+pub fn run_batch<F>(files: &[PathBuf], on_error: OnError, mut op: F) -> Result<BatchReport>
+where
+    F: FnMut(&Path) -> Result<()>,
+{
+    let mut report = BatchReport::default();
+    for path in files {
+        match op(path) {
+            Ok(()) => report.succeeded.push(path.clone()),
+            Err(e) if on_error == OnError::Stop => {
+                return Err(format!("{}: {}", path.display(), e).into());
+            }
+            Err(e) => report.failed.push((path.clone(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Encodes `secret` into a new chunk right before IEND, saving in place
+pub fn encode_into_file(
+    path: &Path,
+    chunk_type: &str,
+    secret: &str,
+    keep_trailing_data: bool,
+) -> Result<()> {
+    let mut png = Png::from_file(path)?;
+    let chunk = Chunk::new(ChunkType::from_str(chunk_type)?, secret.as_bytes().to_vec());
+    let index = png.resolve_insert_index(None)?;
+    png.insert_chunk(index, chunk);
+    crate::save_png(&png, path, keep_trailing_data)
+}
+
+/// Removes the first chunk of `chunk_type`, saving in place
+pub fn remove_from_file(path: &Path, chunk_type: &str, keep_trailing_data: bool) -> Result<()> {
+    let mut png = Png::from_file(path)?;
+    png.remove_first_chunk(chunk_type)?;
+    crate::save_png(&png, path, keep_trailing_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_mixed_batch_report() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let png = Png::from_chunks(vec![Chunk::from_type_and_str("IEND", "").unwrap()]);
+        let files: Vec<PathBuf> = ["a.png", "b.png", "broken.png", "missing.png"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        png.save(&files[0]).unwrap();
+        png.save(&files[1]).unwrap();
+        fs::write(&files[2], b"not a png").unwrap();
+
+        let report = run_batch(&files, OnError::Continue, |path| {
+            encode_into_file(path, "ruSt", "hi", false)
+        })
+        .unwrap();
+        assert_eq!(report.succeeded, files[..2].to_vec());
+        assert_eq!(report.failed.len(), 2);
+        assert!(report.has_failures());
+        assert!(report.summary().starts_with("2 succeeded, 2 failed"));
+
+        let report = run_batch(&files[..2], OnError::Continue, |path| {
+            remove_from_file(path, "ruSt", false)
+        })
+        .unwrap();
+        assert!(!report.has_failures());
+        assert_eq!(report.summary(), "2 succeeded, 0 failed");
+    }
+
+    #[test]
+    fn test_on_error_policies() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let png = Png::from_chunks(vec![Chunk::from_type_and_str("IEND", "").unwrap()]);
+        let files: Vec<PathBuf> = ["a.png", "broken.png", "c.png"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        png.save(&files[0]).unwrap();
+        fs::write(&files[1], b"not a png").unwrap();
+        png.save(&files[2]).unwrap();
+
+        let mut attempted = Vec::new();
+        let err = run_batch(&files, OnError::Stop, |path| {
+            attempted.push(path.to_path_buf());
+            encode_into_file(path, "ruSt", "hi", false)
+        })
+        .unwrap_err();
+        assert!(err.to_string().starts_with(&files[1].display().to_string()));
+        assert_eq!(attempted, files[..2].to_vec());
+
+        let report = run_batch(&files, OnError::Continue, |path| {
+            encode_into_file(path, "ruSt", "hi", false)
+        })
+        .unwrap();
+        assert_eq!(report.succeeded, vec![files[0].clone(), files[2].clone()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, files[1]);
+        assert!(report.has_failures());
+
+        assert_eq!("continue".parse::<OnError>(), Ok(OnError::Continue));
+        assert!("skip".parse::<OnError>().is_err());
+    }
+
+    #[test]
+    fn test_batch_keeps_trailing_data() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let png = Png::from_chunks(vec![Chunk::from_type_and_str("IEND", "").unwrap()]);
+        let mut bytes = png.as_bytes();
+        bytes.extend_from_slice(b"appended thumbnail");
+        let path = dir.join("a.png");
+        fs::write(&path, &bytes).unwrap();
+
+        encode_into_file(&path, "ruSt", "hi", true).unwrap();
+        assert!(fs::read(&path).unwrap().ends_with(b"appended thumbnail"));
+        remove_from_file(&path, "ruSt", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+    }
+}
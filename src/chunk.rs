@@ -1,24 +1,136 @@
 #![allow(unused_imports, dead_code)]
 use std::convert::TryFrom;
 use std::fmt;
-use crc::{Crc, CRC_32_ISO_HDLC};
+use std::io::{Read, Write};
+use std::str::FromStr;
 
+use crc::{CRC_32_BZIP2, CRC_32_ISCSI, CRC_32_ISO_HDLC, Crc};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::chunk_registry::is_known_chunk_type;
 use crate::chunk_type::ChunkType;
+use crate::error::PngError;
 use crate::{Error, Result};
 
 const PNG_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+const BZIP2_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_BZIP2);
+const ISCSI_CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISCSI);
+
+/// CRC-32 variant used to checksum chunks. Only `IsoHdlc` is what the PNG
+/// spec requires; the others are for experimentation and produce files that
+/// standard decoders reject as corrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrcAlgorithm {
+    #[default]
+    IsoHdlc,
+    Bzip2,
+    Iscsi,
+}
+
+impl CrcAlgorithm {
+    fn crc(&self) -> &'static Crc<u32> {
+        match self {
+            CrcAlgorithm::IsoHdlc => &PNG_CRC,
+            CrcAlgorithm::Bzip2 => &BZIP2_CRC,
+            CrcAlgorithm::Iscsi => &ISCSI_CRC,
+        }
+    }
+}
+
+impl FromStr for CrcAlgorithm {
+    type Err = String;
 
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "iso-hdlc" => Ok(CrcAlgorithm::IsoHdlc),
+            "bzip2" => Ok(CrcAlgorithm::Bzip2),
+            "iscsi" => Ok(CrcAlgorithm::Iscsi),
+            other => Err(format!(
+                "Unknown CRC algorithm '{}', expected iso-hdlc, bzip2 or iscsi",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Chunk { 
     length: u32,
     chunk_type: ChunkType,
     data: Vec<u8>,
     crc: u32,
+    crc_algorithm: CrcAlgorithm,
+}
+
+/// Summary statistics over a chunk's data bytes, see `Chunk::data_stats`.
+/// Byte fields are 0 for empty data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataStats {
+    pub len: usize,
+    pub entropy: f64,
+    pub unique_bytes: usize,
+    pub max_byte: u8,
+    pub min_byte: u8,
+    pub mean_byte: f64,
+    pub zero_byte_ratio: f64,
+}
+
+/// File formats recognizable by their leading magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MagicType {
+    Png,
+    Jpeg,
+    Pdf,
+    Zip,
+    Gif,
+    Webp,
+}
+
+impl MagicType {
+    pub fn magic_bytes(&self) -> &'static [u8] {
+        match self {
+            MagicType::Png => b"\x89PNG",
+            MagicType::Jpeg => b"\xFF\xD8",
+            MagicType::Pdf => b"%PDF",
+            MagicType::Zip => b"PK\x03\x04",
+            MagicType::Gif => b"GIF8",
+            MagicType::Webp => b"RIFF",
+        }
+    }
 }
 
 impl TryFrom<&[u8]> for Chunk {
     type Error = Error;
     
     fn try_from(value: &[u8]) -> Result<Self> {
+        Chunk::try_from_with_crc(value, CrcAlgorithm::default())
+    }
+}
+
+impl Chunk {
+    /// Parses a chunk whose CRC was computed with `crc_algorithm`
+    pub fn try_from_with_crc(value: &[u8], crc_algorithm: CrcAlgorithm) -> Result<Chunk> {
+        let mut chunk = Chunk::try_from_unverified(value)?;
+
+        let expected_crc = Chunk::compute_crc_with(&chunk.chunk_type, &chunk.data, crc_algorithm);
+        if chunk.crc != expected_crc {
+            return Err(PngError::CrcMismatch {
+                expected: expected_crc,
+                actual: chunk.crc,
+            }
+            .into());
+        }
+
+        chunk.crc_algorithm = crc_algorithm;
+        Ok(chunk)
+    }
+
+    /// Parses a chunk keeping its stored CRC even when it is wrong, for
+    /// auditing with `verify_crc`. Edits recompute the CRC as usual.
+    #[allow(clippy::needless_return)]
+    pub fn try_from_unverified(value: &[u8]) -> Result<Chunk> {
         if value.len() < 12 {
             return Err("Chunk data must be at least 12 bytes long".into());
         }     
@@ -31,36 +143,27 @@ impl TryFrom<&[u8]> for Chunk {
             }
             
             let bytes_array: [u8; 4] = value[4..8].try_into().unwrap();
-            let chunk_type: ChunkType = ChunkType::try_from(bytes_array).unwrap();
+            let chunk_type: ChunkType = ChunkType::try_from(bytes_array)
+                .map_err(|reason| PngError::InvalidChunkType(bytes_array, reason))?;
             
             let end_data: usize = 8 + length as usize;
             let data: Vec<u8> = value [8..end_data].to_vec();
  
             let crc: u32 = u32::from_be_bytes(value[end_data..end_data + 4].try_into().unwrap());
-            
-            let expected_crc = PNG_CRC.checksum(
-                &chunk_type.bytes()
-                    .iter()
-                    .chain(data.iter())
-                    .copied()
-                    .collect::<Vec<u8>>(),    
-            );
-            
-            if crc != expected_crc {
-                return Err(format!("CRC mismatch: expected {}, got {}", expected_crc, crc).into());
-            }
 
             return Ok(Chunk {
                 length,
                 chunk_type,
                 data,
                 crc,
+                crc_algorithm: CrcAlgorithm::default(),
             })
         }
     }
 }
 
 impl fmt::Display for Chunk {
+    #[allow(clippy::write_with_newline)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Length: {}\n", self.length)?;
         write!(f, "Chunk Type: {}\n", self.chunk_type)?;
@@ -71,42 +174,653 @@ impl fmt::Display for Chunk {
 }
 
 impl Chunk {
+    /// The spec CRC of a chunk with this type and data, without building one
+    pub fn compute_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        Chunk::compute_crc_with(chunk_type, data, CrcAlgorithm::IsoHdlc)
+    }
+
+    /// Like `compute_crc`, but with `crc_algorithm`. Every chunk CRC in the
+    /// crate is computed here.
+    pub fn compute_crc_with(
+        chunk_type: &ChunkType,
+        data: &[u8],
+        crc_algorithm: CrcAlgorithm,
+    ) -> u32 {
+        let mut digest = crc_algorithm.crc().digest();
+        digest.update(&chunk_type.bytes());
+        digest.update(data);
+        digest.finalize()
+    }
+
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
+        Chunk::new_with_crc(chunk_type, data, CrcAlgorithm::default())
+    }
+
+    /// Like `new`, but checksums with `crc_algorithm`. Later edits through
+    /// `set_type`/`set_data` keep using the same algorithm.
+    pub fn new_with_crc(
+        chunk_type: ChunkType,
+        data: Vec<u8>,
+        crc_algorithm: CrcAlgorithm,
+    ) -> Chunk {
         let length: u32 = data.len() as u32;
-        
-        let crc = PNG_CRC.checksum(
-            &chunk_type.bytes()
-                .iter()
-                .chain(data.iter())
-                .copied()
-                .collect::<Vec<u8>>(),
-        );
+        let crc = Chunk::compute_crc_with(&chunk_type, &data, crc_algorithm);
 
         Chunk {
             length,
             chunk_type,
             data,
             crc,
+            crc_algorithm,
         }
     }
-    
+
     pub fn length(&self) -> u32 { self.length }
+    /// Serialized size: data plus 4 length, 4 type and 4 CRC bytes
+    pub fn size_on_disk(&self) -> u32 { self.length + 12 }
+    pub fn size_on_disk_u64(&self) -> u64 { self.length as u64 + 12 }
     pub fn chunk_type(&self) -> &ChunkType { &self.chunk_type }
     pub fn data(&self) -> &[u8] { &self.data }
+    /// Consumes the chunk, returning its data without a copy
+    pub fn into_data(self) -> Vec<u8> { self.data }
     pub fn crc(&self) -> u32 { self.crc }
     pub fn data_as_string(&self) -> Result<String> {
         String::from_utf8(self.data.clone())
             .map_err(|e| format!("Failed to convert chunk data to string :{}", e).into())
     }
+    /// Data decoded as UTF-8 and split into lines, without `\n` or `\r\n` endings
+    pub fn data_as_lines(&self) -> Result<Vec<String>> {
+        Ok(self.data_as_string()?.lines().map(String::from).collect())
+    }
+    pub fn data_line_count(&self) -> Result<usize> {
+        Ok(self.data_as_lines()?.len())
+    }
     pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.as_bytes_without_crc();
+        bytes.extend(self.crc.to_be_bytes().iter());
+
+        bytes
+    }
+
+    /// Length, type and data, leaving out the trailing CRC field
+    pub fn as_bytes_without_crc(&self) -> Vec<u8> {
         let mut bytes: Vec<u8> = Vec::new();
         bytes.extend(self.length.to_be_bytes().iter());
         bytes.extend(self.chunk_type.bytes().iter());
         bytes.extend(self.data.iter());
-        bytes.extend(self.crc.to_be_bytes().iter());
 
         bytes
     }
+
+    /// Rebuilds a chunk from `as_bytes_without_crc` output and a CRC kept
+    /// elsewhere, failing if the CRC doesn't match
+    pub fn from_bytes_with_external_crc(data: &[u8], crc: u32) -> Result<Chunk> {
+        let mut bytes = data.to_vec();
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        Chunk::try_from(bytes.as_ref())
+    }
+
+    pub fn data_matches_magic(&self, magic: MagicType) -> bool {
+        self.data.starts_with(magic.magic_bytes())
+    }
+
+    /// Whether the type is one of the chunk types defined by the PNG spec
+    pub fn is_known_type(&self) -> bool {
+        is_known_chunk_type(&self.chunk_type)
+    }
+
+    pub fn is_custom_type(&self) -> bool {
+        !self.is_known_type()
+    }
+
+    /// Whether this is one of the spec text chunks: `tEXt`, `zTXt` or `iTXt`
+    pub fn is_spec_text_chunk(&self) -> bool {
+        matches!(&self.chunk_type.bytes(), b"tEXt" | b"zTXt" | b"iTXt")
+    }
+
+    /// Parses a spec text chunk into `(keyword, text)`
+    pub fn parse_spec_text(&self) -> Result<(String, String)> {
+        let (keyword, rest) =
+            split_null(&self.data).ok_or("Text chunk is missing the keyword separator")?;
+        let keyword = latin1_to_string(keyword);
+
+        match &self.chunk_type.bytes() {
+            b"tEXt" => Ok((keyword, latin1_to_string(rest))),
+            b"zTXt" => {
+                let (&method, compressed) = rest
+                    .split_first()
+                    .ok_or("zTXt chunk is missing the compression method")?;
+                if method != 0 {
+                    return Err(format!("Unknown zTXt compression method {}", method).into());
+                }
+                Ok((keyword, latin1_to_string(&inflate(compressed)?)))
+            }
+            b"iTXt" => {
+                if rest.len() < 2 {
+                    return Err("iTXt chunk is missing the compression fields".into());
+                }
+                let (flag, method) = (rest[0], rest[1]);
+                let (_language, rest) = split_null(&rest[2..])
+                    .ok_or("iTXt chunk is missing the language tag separator")?;
+                let (_translated, text) = split_null(rest)
+                    .ok_or("iTXt chunk is missing the translated keyword separator")?;
+
+                let text = match (flag, method) {
+                    (0, _) => text.to_vec(),
+                    (1, 0) => inflate(text)?,
+                    _ => return Err(format!("Unknown iTXt compression method {}", method).into()),
+                };
+                Ok((keyword, String::from_utf8(text)?))
+            }
+            _ => Err(format!("Chunk type '{}' is not a text chunk", self.chunk_type).into()),
+        }
+    }
+
+    /// Warns when this chunk uses a standard text type (tEXt, zTXt, iTXt) but
+    /// its data doesn't follow that format, so viewers may choke on it
+    pub fn type_collision_warning(&self) -> Option<String> {
+        if !self.is_spec_text_chunk() {
+            return None;
+        }
+
+        let problem = match self.parse_spec_text() {
+            Err(e) => e.to_string(),
+            Ok((keyword, _)) if keyword.is_empty() || keyword.len() > 79 => {
+                format!("keyword must be 1 to 79 bytes, got {}", keyword.len())
+            }
+            Ok(_)
+                if &self.chunk_type.bytes() == b"tEXt"
+                    && split_null(&self.data).is_some_and(|(_, text)| text.contains(&0)) =>
+            {
+                "text contains a null byte".to_string()
+            }
+            Ok(_) => return None,
+        };
+        Some(format!(
+            "data is not a valid '{}' chunk ({}); image viewers may try to read it as text",
+            self.chunk_type, problem
+        ))
+    }
+
+    pub fn data_range(&self, start: usize, end: usize) -> Result<&[u8]> {
+        if start > end || end > self.data.len() {
+            return Err(format!(
+                "Range {}:{} is out of bounds for {} bytes of data",
+                start,
+                end,
+                self.data.len()
+            )
+            .into());
+        }
+        Ok(&self.data[start..end])
+    }
+
+    /// Offset of the first data byte that differs from `other`'s data
+    pub fn data_mismatch_offset(&self, other: &Chunk) -> Option<usize> {
+        first_mismatch(&self.data, &other.data)
+    }
+
+    pub fn from_type_and_str(chunk_type: &str, message: &str) -> Result<Chunk> {
+        Chunk::from_type_and_bytes(chunk_type, message.as_bytes())
+    }
+
+    pub fn from_type_and_bytes(chunk_type: &str, data: &[u8]) -> Result<Chunk> {
+        Ok(Chunk::new(ChunkType::from_str(chunk_type)?, data.to_vec()))
+    }
+
+    pub fn new_from_hex_str(chunk_type: ChunkType, hex_data: &str) -> Result<Chunk> {
+        Ok(Chunk::new(chunk_type, decode_hex(hex_data)?))
+    }
+
+    /// Serializes `value` as a TOML document stored as UTF-8 bytes. The
+    /// value must be a table (a struct or map) at the top level.
+    #[cfg(feature = "toml-payload")]
+    pub fn new_from_toml<T: serde::Serialize>(chunk_type: ChunkType, value: &T) -> Result<Chunk> {
+        let text = toml::to_string(value)
+            .map_err(|e| format!("Failed to serialize TOML payload: {}", e))?;
+        Ok(Chunk::new(chunk_type, text.into_bytes()))
+    }
+
+    /// Parses the data as a UTF-8 TOML document, see `new_from_toml`
+    #[cfg(feature = "toml-payload")]
+    pub fn data_as_toml<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        toml::from_str(&self.data_as_string()?)
+            .map_err(|e| format!("Failed to parse TOML payload: {}", e).into())
+    }
+
+    pub fn set_type(&mut self, chunk_type: ChunkType) {
+        self.chunk_type = chunk_type;
+        self.refresh();
+    }
+
+    /// Returns a copy of this chunk under `new_type`, leaving `self` untouched
+    pub fn copy_with_new_type(&self, new_type: ChunkType) -> Chunk {
+        let mut copy = self.clone();
+        copy.set_type(new_type);
+        copy
+    }
+
+    /// Whether the stored CRC is the spec CRC of the type and data
+    pub fn verify_crc(&self) -> bool {
+        self.crc == Chunk::compute_crc(&self.chunk_type, &self.data)
+    }
+
+    /// Checks that the length field matches the data and the CRC is the spec CRC
+    pub fn validate(&self) -> Result<()> {
+        if self.length as usize != self.data.len() {
+            return Err(PngError::LengthMismatch {
+                declared: self.length,
+                actual: self.data.len(),
+            }
+            .into());
+        }
+        if !self.verify_crc() {
+            let expected = Chunk::compute_crc(&self.chunk_type, &self.data);
+            return Err(PngError::CrcMismatch {
+                expected,
+                actual: self.crc,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Resets the CRC to the spec value, dropping any alternate `CrcAlgorithm`
+    pub fn repair_crc(&mut self) {
+        self.set_crc_algorithm(CrcAlgorithm::IsoHdlc);
+    }
+
+    /// Recomputes the CRC with `crc_algorithm` and keeps using it for later edits
+    pub fn set_crc_algorithm(&mut self, crc_algorithm: CrcAlgorithm) {
+        self.crc_algorithm = crc_algorithm;
+        self.refresh();
+    }
+
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+        self.refresh();
+    }
+
+    /// Prefixes `payload` with its big-endian CRC32 so decode can detect corruption
+    pub fn add_integrity_header(payload: &[u8]) -> Vec<u8> {
+        let mut data = payload_crc(payload).to_be_bytes().to_vec();
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// Splits data written by `add_integrity_header` into the payload and
+    /// whether its stored checksum still matches
+    pub fn integrity_payload(&self) -> Result<(&[u8], bool)> {
+        if self.data.len() < 4 {
+            return Err("Chunk data is too short to hold an integrity header".into());
+        }
+        let stored = u32::from_be_bytes(self.data[0..4].try_into().unwrap());
+        let payload = &self.data[4..];
+        Ok((payload, payload_crc(payload) == stored))
+    }
+
+    /// Wraps `message` in a `[u32 big-endian length][bytes]` record
+    pub fn frame_message(message: &[u8]) -> Vec<u8> {
+        let mut framed = (message.len() as u32).to_be_bytes().to_vec();
+        framed.extend_from_slice(message);
+        framed
+    }
+
+    /// Splits data written as consecutive `frame_message` records
+    pub fn framed_messages(&self) -> Result<Vec<&[u8]>> {
+        let mut messages = Vec::new();
+        let mut offset: usize = 0;
+
+        while offset < self.data.len() {
+            if offset + 4 > self.data.len() {
+                return Err(format!("Truncated frame header at offset {}", offset).into());
+            }
+            let len =
+                u32::from_be_bytes(self.data[offset..offset + 4].try_into().unwrap()) as usize;
+            let start = offset + 4;
+
+            if start + len > self.data.len() {
+                return Err(format!(
+                    "Frame at offset {} needs {} bytes but only {} remain",
+                    offset,
+                    len,
+                    self.data.len() - start
+                )
+                .into());
+            }
+            messages.push(&self.data[start..start + len]);
+            offset = start + len;
+        }
+
+        Ok(messages)
+    }
+
+    /// Concatenates `messages` as `[LEB128 length][bytes]` records, so the
+    /// boundaries survive being stored in one chunk
+    pub fn encode_length_prefixed(messages: &[&[u8]]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for message in messages {
+            let mut len = message.len() as u64;
+            loop {
+                let byte = (len & 0x7f) as u8;
+                len >>= 7;
+                if len == 0 {
+                    encoded.push(byte);
+                    break;
+                }
+                encoded.push(byte | 0x80);
+            }
+            encoded.extend_from_slice(message);
+        }
+        encoded
+    }
+
+    /// Splits data written by `encode_length_prefixed` back into messages
+    pub fn decode_length_prefixed(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut messages = Vec::new();
+        let mut offset: usize = 0;
+
+        while offset < data.len() {
+            let mut len: u64 = 0;
+            let mut shift = 0;
+            loop {
+                let byte = *data
+                    .get(offset)
+                    .ok_or_else(|| format!("Truncated length prefix at offset {}", offset))?;
+                // The tenth byte only has room for bit 63 of a u64
+                if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+                    return Err(format!("Length prefix at offset {} is too long", offset).into());
+                }
+                len |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+                offset += 1;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+
+            let remaining = data.len() - offset;
+            if len > remaining as u64 {
+                return Err(format!(
+                    "Message at offset {} needs {} bytes but only {} remain",
+                    offset, len, remaining
+                )
+                .into());
+            }
+            let end = offset + len as usize;
+            messages.push(data[offset..end].to_vec());
+            offset = end;
+        }
+
+        Ok(messages)
+    }
+
+    /// One-line description, e.g. `[ruSt] len=42 crc=0xABCD1234 ancillary private reserved safe-to-copy`
+    pub fn to_summary_line(&self) -> String {
+        let t = &self.chunk_type;
+        format!(
+            "[{}] len={} crc=0x{:08X} {} {} {} {}",
+            t,
+            self.length,
+            self.crc,
+            if t.is_critical() {
+                "critical"
+            } else {
+                "ancillary"
+            },
+            if t.is_public() { "public" } else { "private" },
+            if t.is_reserved_bit_valid() {
+                "reserved"
+            } else {
+                "!reserved"
+            },
+            if t.is_safe_to_copy() {
+                "safe-to-copy"
+            } else {
+                "unsafe-to-copy"
+            },
+        )
+    }
+
+    /// Count of each byte value in the data, indexed by byte value
+    pub fn data_byte_frequency(&self) -> [u32; 256] {
+        let mut counts = [0u32; 256];
+        for &byte in &self.data {
+            counts[byte as usize] += 1;
+        }
+        counts
+    }
+
+    /// The most common byte and its count, preferring the lowest byte value on ties
+    pub fn most_frequent_byte(&self) -> Option<(u8, u32)> {
+        self.data_byte_frequency()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(&a.0)))
+            .map(|(byte, &count)| (byte as u8, count))
+    }
+
+    pub fn data_as_u32_be(&self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.data_prefix()?))
+    }
+    pub fn data_as_u64_be(&self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.data_prefix()?))
+    }
+    pub fn data_as_i32_be(&self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.data_prefix()?))
+    }
+    pub fn data_as_f32_be(&self) -> Result<f32> {
+        Ok(f32::from_be_bytes(self.data_prefix()?))
+    }
+
+    /// The first 16 data bytes as a UUID
+    pub fn data_as_uuid(&self) -> Result<[u8; 16]> {
+        self.data_prefix()
+    }
+
+    /// The first 16 data bytes formatted as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`
+    pub fn data_uuid_str(&self) -> Result<String> {
+        let uuid = self.data_as_uuid()?;
+        Ok(format!(
+            "{}-{}-{}-{}-{}",
+            encode_hex(&uuid[0..4]),
+            encode_hex(&uuid[4..6]),
+            encode_hex(&uuid[6..8]),
+            encode_hex(&uuid[8..10]),
+            encode_hex(&uuid[10..16]),
+        ))
+    }
+
+    /// A chunk whose data is `uuid` followed by `extra`
+    pub fn new_with_uuid(chunk_type: ChunkType, uuid: [u8; 16], extra: &[u8]) -> Chunk {
+        let mut data = uuid.to_vec();
+        data.extend_from_slice(extra);
+        Chunk::new(chunk_type, data)
+    }
+
+    /// The first `N` data bytes, for the numeric accessors above
+    fn data_prefix<const N: usize>(&self) -> Result<[u8; N]> {
+        self.data
+            .get(..N)
+            .map(|bytes| bytes.try_into().unwrap())
+            .ok_or_else(|| {
+                PngError::DataTooShort {
+                    len: self.data.len(),
+                    needed: N,
+                }
+                .into()
+            })
+    }
+
+    pub fn data_stats(&self) -> DataStats {
+        let frequency = self.data_byte_frequency();
+        let len = self.data.len();
+        let ratio = |count: f64| if len == 0 { 0.0 } else { count / len as f64 };
+
+        DataStats {
+            len,
+            entropy: self.data_entropy(),
+            unique_bytes: frequency.iter().filter(|&&count| count > 0).count(),
+            max_byte: self.data.iter().copied().max().unwrap_or(0),
+            min_byte: self.data.iter().copied().min().unwrap_or(0),
+            mean_byte: ratio(self.data.iter().map(|&b| b as f64).sum()),
+            zero_byte_ratio: ratio(frequency[0] as f64),
+        }
+    }
+
+    /// Each data byte as two values in `0..=15`, high nibble first
+    pub fn iter_data_nibbles(&self) -> impl Iterator<Item = u8> + '_ {
+        self.data.iter().flat_map(|&byte| [byte >> 4, byte & 0x0f])
+    }
+
+    pub fn nibble_histogram(&self) -> [u32; 16] {
+        let mut counts = [0u32; 16];
+        for nibble in self.iter_data_nibbles() {
+            counts[nibble as usize] += 1;
+        }
+        counts
+    }
+
+    /// Shannon entropy of the data in bits per byte, from 0.0 to 8.0.
+    /// Values above ~7.5 suggest compressed or encrypted content.
+    pub fn data_entropy(&self) -> f64 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+
+        let len = self.data.len() as f64;
+        self.data_byte_frequency()
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / len;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    pub fn pad_data_to(&mut self, target_len: usize, pad_byte: u8) -> Result<()> {
+        if self.data.len() > target_len {
+            return Err(PngError::DataTooLarge {
+                len: self.data.len(),
+                max: target_len,
+            }
+            .into());
+        }
+
+        self.data.resize(target_len, pad_byte);
+        self.refresh();
+        Ok(())
+    }
+
+    /// Pads the data up to the next multiple of `alignment`, which must be a
+    /// power of 2. Returns the number of pad bytes added.
+    pub fn pad_to_alignment(&mut self, alignment: usize, pad_byte: u8) -> Result<usize> {
+        if !alignment.is_power_of_two() {
+            return Err(PngError::InvalidAlignment(alignment).into());
+        }
+
+        let padding = self.data.len().next_multiple_of(alignment) - self.data.len();
+        self.pad_data_to(self.data.len() + padding, pad_byte)?;
+        Ok(padding)
+    }
+
+    /// Returns `nonce_len` bytes from the OS CSPRNG and a copy of the chunk
+    /// with them prepended to its data, the layout `strip_nonce` splits.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system's random number generator is unavailable.
+    pub fn prepend_nonce(&self, nonce_len: usize) -> (Vec<u8>, Chunk) {
+        let mut nonce = vec![0u8; nonce_len];
+        getrandom::getrandom(&mut nonce).expect("OS random number generator failed");
+
+        let mut data = Vec::with_capacity(nonce_len + self.data.len());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&self.data);
+        (
+            nonce,
+            Chunk::new_with_crc(self.chunk_type.clone(), data, self.crc_algorithm),
+        )
+    }
+
+    /// Splits the data into `(nonce, rest)` at `nonce_len`
+    pub fn strip_nonce(&self, nonce_len: usize) -> Result<(&[u8], &[u8])> {
+        if self.data.len() < nonce_len {
+            return Err(PngError::DataTooShort {
+                len: self.data.len(),
+                needed: nonce_len,
+            }
+            .into());
+        }
+        Ok(self.data.split_at(nonce_len))
+    }
+
+    /// Recomputes `length` and `crc` after `data` or `chunk_type` changed
+    fn refresh(&mut self) {
+        self.length = self.data.len() as u32;
+        self.crc = Chunk::compute_crc_with(&self.chunk_type, &self.data, self.crc_algorithm);
+    }
+}
+
+fn split_null(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let i = bytes.iter().position(|&b| b == 0)?;
+    Some((&bytes[..i], &bytes[i + 1..]))
+}
+
+fn latin1_to_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Zlib-compresses `data` at `level` (0 stores, 9 is smallest)
+pub fn deflate(data: &[u8], level: u32) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn inflate(compressed: &[u8]) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(compressed).read_to_end(&mut inflated)?;
+    Ok(inflated)
+}
+
+/// The PNG spec CRC over `chunk_type` followed by `data`
+pub fn crc_for(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+    Chunk::compute_crc(chunk_type, data)
+}
+
+/// Offset of the first byte where `a` and `b` differ. When one is a prefix of
+/// the other, that's the shorter length; `None` if they are equal.
+pub fn first_mismatch(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(offset) => Some(offset),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// CRC32 of a message payload, as used by integrity headers and decode --show-hash
+pub fn payload_crc(payload: &[u8]) -> u32 {
+    PNG_CRC.checksum(payload)
+}
+
+/// Encodes bytes as a lowercase hex string, the inverse of `decode_hex`
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string such as `"deadbeef"` into bytes
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(PngError::InvalidHex(hex.to_string()).into());
+    }
+
+    Ok((0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect())
 }
 
 #[cfg(test)]
@@ -148,6 +862,14 @@ mod tests {
         assert_eq!(chunk.length(), 42);
     }
 
+    #[test]
+    fn test_chunk_size_on_disk() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.size_on_disk(), 54);
+        assert_eq!(chunk.size_on_disk_u64(), 54);
+        assert_eq!(chunk.size_on_disk() as usize, chunk.as_bytes().len());
+    }
+
     #[test]
     fn test_chunk_type() {
         let chunk = testing_chunk();
@@ -216,6 +938,350 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_invalid_chunk_type_from_bytes_names_byte() {
+        let mut bytes = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"hi".to_vec()).as_bytes();
+        bytes[6] = b' ';
+
+        let err = Chunk::try_from(bytes.as_ref()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PngError>(),
+            Some(PngError::InvalidChunkType(..))
+        ));
+        assert!(
+            err.to_string()
+                .contains("byte 2 (' ') is not an ASCII letter"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_empty_chunk_new() {
+        let chunk = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        assert_eq!(chunk.length(), 0);
+        assert_eq!(chunk.crc(), 0xAE426082);
+        assert!(chunk.data().is_empty());
+    }
+
+    #[test]
+    fn test_empty_chunk_as_bytes() {
+        let chunk = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        assert_eq!(
+            chunk.as_bytes(),
+            vec![0, 0, 0, 0, 73, 69, 78, 68, 0xAE, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn test_empty_chunk_try_from() {
+        let bytes = [0, 0, 0, 0, 73, 69, 78, 68, 0xAE, 0x42, 0x60, 0x82];
+        let chunk = Chunk::try_from(&bytes[..]).unwrap();
+        assert_eq!(chunk.length(), 0);
+        assert_eq!(chunk.chunk_type().to_string(), "IEND");
+        assert_eq!(chunk.as_bytes(), bytes.to_vec());
+
+        assert!(Chunk::try_from(&bytes[..11]).is_err());
+        let mut too_long = bytes.to_vec();
+        too_long.push(0);
+        assert!(Chunk::try_from(too_long.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_pad_data_to() {
+        let mut chunk = testing_chunk();
+        chunk.pad_data_to(64, 0).unwrap();
+
+        assert_eq!(chunk.length(), 64);
+        assert_eq!(chunk.data().len(), 64);
+        assert!(chunk.data()[42..].iter().all(|&b| b == 0));
+        assert!(Chunk::try_from(chunk.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_pad_data_to_too_small() {
+        let mut chunk = testing_chunk();
+        let err = chunk.pad_data_to(10, 0).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::DataTooLarge { len: 42, max: 10 })
+        );
+        assert_eq!(chunk.length(), 42);
+    }
+
+    fn zlib(text: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_data_as_lines() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"first\r\nsecond\n\nfourth\n".to_vec(),
+        );
+        assert_eq!(
+            chunk.data_as_lines().unwrap(),
+            vec!["first", "second", "", "fourth"]
+        );
+        assert_eq!(chunk.data_line_count().unwrap(), 4);
+
+        assert_eq!(testing_chunk().data_line_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_data_as_lines_invalid_utf8() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0xff, b'\n']);
+        assert!(chunk.data_as_lines().is_err());
+    }
+
+    #[test]
+    fn test_data_matches_magic() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"%PDF-1.7 ...".to_vec(),
+        );
+        assert!(chunk.data_matches_magic(MagicType::Pdf));
+        assert!(!chunk.data_matches_magic(MagicType::Zip));
+
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            vec![0xFF, 0xD8, 0xFF, 0xE0],
+        );
+        assert!(chunk.data_matches_magic(MagicType::Jpeg));
+
+        assert!(!testing_chunk().data_matches_magic(MagicType::Png));
+    }
+
+    #[test]
+    fn test_is_known_type() {
+        let chunk = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![0, 0, 177, 143]);
+        assert!(chunk.is_known_type());
+        assert!(!chunk.is_custom_type());
+
+        let chunk = testing_chunk();
+        assert!(!chunk.is_known_type());
+        assert!(chunk.is_custom_type());
+    }
+
+    #[test]
+    fn test_parse_text_chunk() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Author\0Caf\xe9".to_vec(),
+        );
+        assert!(chunk.is_spec_text_chunk());
+        assert_eq!(
+            chunk.parse_spec_text().unwrap(),
+            ("Author".to_string(), "Caf\u{e9}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_ztxt_chunk() {
+        let mut data = b"Comment\0\0".to_vec();
+        data.extend(zlib(b"compressed comment"));
+        let chunk = Chunk::new(ChunkType::from_str("zTXt").unwrap(), data);
+
+        assert!(chunk.is_spec_text_chunk());
+        assert_eq!(
+            chunk.parse_spec_text().unwrap(),
+            ("Comment".to_string(), "compressed comment".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_itxt_chunk() {
+        let plain = Chunk::new(
+            ChunkType::from_str("iTXt").unwrap(),
+            "Title\0\0\0en\0Titel\0Grüße".as_bytes().to_vec(),
+        );
+        assert_eq!(
+            plain.parse_spec_text().unwrap(),
+            ("Title".to_string(), "Grüße".to_string())
+        );
+
+        let mut data = b"Title\0\x01\0\0\0".to_vec();
+        data.extend(zlib("Grüße".as_bytes()));
+        let compressed = Chunk::new(ChunkType::from_str("iTXt").unwrap(), data);
+        assert_eq!(
+            compressed.parse_spec_text().unwrap(),
+            ("Title".to_string(), "Grüße".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_text_rejects_other_chunks() {
+        let chunk = testing_chunk();
+        assert!(!chunk.is_spec_text_chunk());
+        assert!(chunk.parse_spec_text().is_err());
+    }
+
+    #[test]
+    fn test_data_range() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.data_range(8, 13).unwrap(), b"where");
+        assert_eq!(chunk.data_range(0, 42).unwrap(), chunk.data());
+        assert!(chunk.data_range(5, 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_data_range_out_of_bounds() {
+        let chunk = testing_chunk();
+        assert!(chunk.data_range(40, 43).is_err());
+        assert!(chunk.data_range(10, 5).is_err());
+    }
+
+    #[test]
+    fn test_new_from_hex_str() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new_from_hex_str(chunk_type, "deadbeef").unwrap();
+        assert_eq!(chunk.data(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(chunk.length(), 4);
+    }
+
+    #[test]
+    fn test_new_from_invalid_hex_str() {
+        for hex in ["abc", "zz", "dead beef"] {
+            let chunk_type = ChunkType::from_str("RuSt").unwrap();
+            let err = Chunk::new_from_hex_str(chunk_type, hex).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<PngError>(),
+                Some(&PngError::InvalidHex(hex.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_type_to_ancillary() {
+        let mut chunk = testing_chunk();
+        assert!(chunk.chunk_type().is_critical());
+
+        let ancillary = chunk.chunk_type().with_critical(false);
+        chunk.set_type(ancillary);
+
+        assert!(!chunk.chunk_type().is_critical());
+        assert_ne!(chunk.crc(), 2882656334);
+        assert!(Chunk::try_from(chunk.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_to_summary_line() {
+        let chunk = testing_chunk();
+        assert_eq!(
+            chunk.to_summary_line(),
+            "[RuSt] len=42 crc=0xABD1D84E critical private reserved safe-to-copy"
+        );
+
+        let chunk = Chunk::new(ChunkType::from_str("gAma").unwrap(), Vec::new());
+        assert!(
+            chunk
+                .to_summary_line()
+                .ends_with("ancillary public !reserved safe-to-copy")
+        );
+    }
+
+    #[test]
+    fn test_data_byte_frequency() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"abracadabra".to_vec(),
+        );
+        let counts = chunk.data_byte_frequency();
+
+        assert_eq!(counts[b'a' as usize], 5);
+        assert_eq!(counts[b'b' as usize], 2);
+        assert_eq!(counts[b'z' as usize], 0);
+        assert_eq!(counts.iter().sum::<u32>(), 11);
+        assert_eq!(chunk.most_frequent_byte(), Some((b'a', 5)));
+    }
+
+    #[test]
+    fn test_most_frequent_byte_edge_cases() {
+        let empty = Chunk::new(ChunkType::from_str("ruSt").unwrap(), Vec::new());
+        assert_eq!(empty.most_frequent_byte(), None);
+
+        let tie = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![9, 3, 9, 3]);
+        assert_eq!(tie.most_frequent_byte(), Some((3, 2)));
+    }
+
+    #[test]
+    fn test_data_entropy_zero() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0; 128]);
+        assert_eq!(chunk.data_entropy(), 0.0);
+
+        let empty = Chunk::new(ChunkType::from_str("RuSt").unwrap(), Vec::new());
+        assert_eq!(empty.data_entropy(), 0.0);
+    }
+
+    #[test]
+    fn test_data_entropy_high() {
+        let data: Vec<u8> = (0..=255).collect();
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), data);
+        assert!((chunk.data_entropy() - 8.0).abs() < 1e-9);
+
+        let text = testing_chunk();
+        assert!(text.data_entropy() < 5.0);
+    }
+
+    #[test]
+    fn test_copy_with_new_type() {
+        let chunk = testing_chunk();
+        let copy = chunk.copy_with_new_type(ChunkType::from_str("ruSt").unwrap());
+
+        assert_eq!(copy.chunk_type().to_string(), "ruSt");
+        assert_eq!(copy.data(), chunk.data());
+        assert!(Chunk::try_from(copy.as_bytes().as_ref()).is_ok());
+
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_integrity_header_ok() {
+        let data = Chunk::add_integrity_header(b"secret payload");
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), data);
+
+        let (payload, ok) = chunk.integrity_payload().unwrap();
+        assert_eq!(payload, b"secret payload");
+        assert!(ok);
+    }
+
+    #[test]
+    fn test_integrity_header_detects_flipped_byte() {
+        let mut data = Chunk::add_integrity_header(b"secret payload");
+        data[6] ^= 0x01;
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), data);
+
+        let (_, ok) = chunk.integrity_payload().unwrap();
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_framed_messages_round_trip() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data: Vec<u8> = [&b"first"[..], b"", b"third message"]
+            .iter()
+            .flat_map(|m| Chunk::frame_message(m))
+            .collect();
+        let chunk = Chunk::new(chunk_type, data);
+
+        let messages = chunk.framed_messages().unwrap();
+        assert_eq!(messages, vec![&b"first"[..], b"", b"third message"]);
+    }
+
+    #[test]
+    fn test_framed_messages_truncated() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let mut data = Chunk::frame_message(b"hello");
+        data.pop();
+        let chunk = Chunk::new(chunk_type, data);
+
+        assert!(chunk.framed_messages().is_err());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -236,4 +1302,351 @@ mod tests {
         
         let _chunk_string = format!("{}", chunk);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_default_crc_algorithm_matches_spec() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = b"This is where your secret message will be!";
+        assert_eq!(CrcAlgorithm::default(), CrcAlgorithm::IsoHdlc);
+        assert_eq!(
+            Chunk::compute_crc_with(&chunk_type, data, CrcAlgorithm::default()),
+            2882656334
+        );
+        assert_eq!(Chunk::compute_crc(&chunk_type, data), 2882656334);
+    }
+
+    #[test]
+    fn test_alternate_crc_algorithm_differs() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = b"This is where your secret message will be!".to_vec();
+        let chunk = Chunk::new_with_crc(chunk_type, data, CrcAlgorithm::Bzip2);
+        assert_ne!(chunk.crc(), 2882656334);
+        assert_ne!(
+            chunk.crc(),
+            Chunk::compute_crc_with(chunk.chunk_type(), chunk.data(), CrcAlgorithm::Iscsi)
+        );
+    }
+
+    #[test]
+    fn test_alternate_crc_algorithm_round_trip() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let mut chunk = Chunk::new_with_crc(chunk_type, b"hidden".to_vec(), CrcAlgorithm::Iscsi);
+        chunk.set_data(b"still hidden".to_vec());
+
+        let bytes = chunk.as_bytes();
+        let parsed = Chunk::try_from_with_crc(&bytes, CrcAlgorithm::Iscsi).unwrap();
+        assert_eq!(parsed.crc(), chunk.crc());
+
+        let err = Chunk::try_from(bytes.as_ref()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PngError>(),
+            Some(PngError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_crc_algorithm_from_str() {
+        assert_eq!(
+            CrcAlgorithm::from_str("bzip2").unwrap(),
+            CrcAlgorithm::Bzip2
+        );
+        assert!(CrcAlgorithm::from_str("crc64").is_err());
+    }
+
+    #[test]
+    fn test_verify_and_repair_crc() {
+        assert!(testing_chunk().verify_crc());
+
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let mut chunk = Chunk::new_with_crc(chunk_type, b"hidden".to_vec(), CrcAlgorithm::Bzip2);
+        assert!(!chunk.verify_crc());
+
+        chunk.repair_crc();
+        assert!(chunk.verify_crc());
+        assert_eq!(
+            chunk.crc(),
+            Chunk::compute_crc(chunk.chunk_type(), chunk.data())
+        );
+    }
+
+    #[test]
+    fn test_into_data() {
+        let chunk = testing_chunk();
+        let expected = chunk.data().to_vec();
+        assert_eq!(chunk.into_data(), expected);
+    }
+
+    #[test]
+    fn test_deflate_levels_round_trip() {
+        let data = "secret ".repeat(200).into_bytes();
+        let stored = deflate(&data, 0).unwrap();
+        let smallest = deflate(&data, 9).unwrap();
+
+        assert_eq!(inflate(&stored).unwrap(), data);
+        assert_eq!(inflate(&smallest).unwrap(), data);
+        assert!(smallest.len() <= stored.len());
+    }
+
+    #[test]
+    fn test_type_collision_warning() {
+        let binary = vec![0x00, 0xff, 0x10, 0x00, 0x7f];
+        let text = Chunk::new(ChunkType::from_str("tEXt").unwrap(), binary.clone());
+        assert!(text.type_collision_warning().is_some());
+
+        let private = Chunk::new(ChunkType::from_str("ruSt").unwrap(), binary);
+        assert!(private.type_collision_warning().is_none());
+
+        let valid = Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Comment\0hello".to_vec(),
+        );
+        assert!(valid.type_collision_warning().is_none());
+    }
+
+    #[test]
+    fn test_data_nibbles() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0xab, 0x0f, 0xa0]);
+        let nibbles: Vec<u8> = chunk.iter_data_nibbles().collect();
+        assert_eq!(nibbles, [0xa, 0xb, 0x0, 0xf, 0xa, 0x0]);
+
+        let histogram = chunk.nibble_histogram();
+        assert_eq!(histogram[0x0], 2);
+        assert_eq!(histogram[0xa], 2);
+        assert_eq!(histogram.iter().sum::<u32>(), 6);
+    }
+
+    #[test]
+    fn test_data_as_numbers() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut data = 0xdeadbeefu32.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        let chunk = Chunk::new(chunk_type.clone(), data);
+
+        assert_eq!(chunk.data_as_u32_be().unwrap(), 0xdeadbeef);
+        assert_eq!(chunk.data_as_i32_be().unwrap(), 0xdeadbeefu32 as i32);
+        assert_eq!(chunk.data_as_u64_be().unwrap(), 0xdeadbeef_00000001);
+
+        let float = Chunk::new(chunk_type.clone(), 1.5f32.to_be_bytes().to_vec());
+        assert_eq!(float.data_as_f32_be().unwrap(), 1.5);
+
+        let short = Chunk::new(chunk_type, vec![1, 2, 3]);
+        let err = short.data_as_u32_be().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::DataTooShort { len: 3, needed: 4 })
+        );
+    }
+
+    #[test]
+    fn test_payload_crc() {
+        assert_eq!(
+            payload_crc(b"The quick brown fox jumps over the lazy dog"),
+            0x414fa339
+        );
+    }
+
+    #[test]
+    fn test_from_type_and_str() {
+        let chunk =
+            Chunk::from_type_and_str("RuSt", "This is where your secret message will be!").unwrap();
+        assert_eq!(chunk.crc(), 2882656334);
+
+        let chunk = Chunk::from_type_and_bytes("ruSt", &[0xde, 0xad]).unwrap();
+        assert_eq!(chunk.data(), [0xde, 0xad]);
+        assert!(Chunk::from_type_and_str("Ru1t", "x").is_err());
+    }
+
+    #[test]
+    fn test_data_stats() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0, 0, 10, 30]);
+        let stats = chunk.data_stats();
+
+        assert_eq!(stats.len, 4);
+        assert_eq!(stats.unique_bytes, 3);
+        assert_eq!(stats.min_byte, 0);
+        assert_eq!(stats.max_byte, 30);
+        assert_eq!(stats.mean_byte, 10.0);
+        assert_eq!(stats.zero_byte_ratio, 0.5);
+        assert_eq!(stats.entropy, 1.5);
+
+        let empty = Chunk::new(ChunkType::from_str("ruSt").unwrap(), Vec::new()).data_stats();
+        assert_eq!(empty.len, 0);
+        assert_eq!(empty.mean_byte, 0.0);
+    }
+
+    #[test]
+    fn test_pad_to_alignment() {
+        let mut chunk = testing_chunk();
+        assert_eq!(chunk.pad_to_alignment(8, 0).unwrap(), 6);
+        assert_eq!(chunk.length(), 48);
+        assert!(chunk.verify_crc());
+        assert_eq!(chunk.pad_to_alignment(16, 0).unwrap(), 0);
+
+        let err = chunk.pad_to_alignment(12, 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::InvalidAlignment(12))
+        );
+    }
+
+    #[test]
+    fn test_data_mismatch_offset() {
+        let a = Chunk::from_type_and_str("ruSt", "secret message").unwrap();
+        let b = Chunk::from_type_and_str("ruSt", "secret massage").unwrap();
+        let c = Chunk::from_type_and_str("ruSt", "secret").unwrap();
+
+        assert_eq!(a.data_mismatch_offset(&b), Some(8));
+        assert_eq!(a.data_mismatch_offset(&c), Some(6));
+        assert_eq!(a.data_mismatch_offset(&a.clone()), None);
+    }
+
+    #[test]
+    fn test_bytes_without_crc_round_trip() {
+        let chunk = testing_chunk();
+        let bytes = chunk.as_bytes_without_crc();
+        assert_eq!(bytes.len(), 8 + 42);
+
+        let rebuilt = Chunk::from_bytes_with_external_crc(&bytes, chunk.crc()).unwrap();
+        assert_eq!(rebuilt.as_bytes(), chunk.as_bytes());
+        assert!(Chunk::from_bytes_with_external_crc(&bytes, 0).is_err());
+    }
+
+    #[test]
+    fn test_crc_for_matches_new() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let data = b"some hidden bytes".to_vec();
+        assert_eq!(
+            crc_for(&chunk_type, &data),
+            Chunk::new(chunk_type, data).crc()
+        );
+    }
+
+    #[test]
+    fn test_uuid_data() {
+        let uuid: [u8; 16] = [
+            0x12, 0x3e, 0x45, 0x67, 0xe8, 0x9b, 0x12, 0xd3, 0xa4, 0x56, 0x42, 0x66, 0x14, 0x17,
+            0x40, 0x00,
+        ];
+        let chunk = Chunk::new_with_uuid(ChunkType::from_str("ruSt").unwrap(), uuid, b"payload");
+
+        assert_eq!(chunk.length(), 23);
+        assert_eq!(chunk.data_as_uuid().unwrap(), uuid);
+        assert_eq!(
+            chunk.data_uuid_str().unwrap(),
+            "123e4567-e89b-12d3-a456-426614174000"
+        );
+
+        let short = Chunk::from_type_and_str("ruSt", "too short").unwrap();
+        let err = short.data_as_uuid().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::DataTooShort { len: 9, needed: 16 })
+        );
+    }
+
+    #[test]
+    fn test_length_prefixed_round_trip() {
+        let long = vec![7u8; 300];
+        let messages: [&[u8]; 3] = [b"first", b"", &long];
+        let encoded = Chunk::encode_length_prefixed(&messages);
+
+        assert_eq!(&encoded[..6], b"\x05first");
+        // 300 needs two LEB128 bytes: 0xAC 0x02
+        assert_eq!(&encoded[7..9], &[0xac, 0x02]);
+        assert_eq!(
+            Chunk::decode_length_prefixed(&encoded).unwrap(),
+            messages.map(|m| m.to_vec())
+        );
+
+        assert!(Chunk::decode_length_prefixed(&[0x05, b'a']).is_err());
+        assert!(Chunk::decode_length_prefixed(&[0x80]).is_err());
+    }
+
+    #[test]
+    fn test_length_prefixed_overlong_prefix() {
+        // Nine continuation bytes then a tenth byte with bits past 63 set
+        let mut overlong = vec![0xff; 9];
+        overlong.push(0x02);
+        let err = Chunk::decode_length_prefixed(&overlong).unwrap_err();
+        assert!(err.to_string().contains("too long"));
+
+        let mut eleven_bytes = vec![0x80; 10];
+        eleven_bytes.push(0x00);
+        let err = Chunk::decode_length_prefixed(&eleven_bytes).unwrap_err();
+        assert!(err.to_string().contains("too long"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(testing_chunk().validate().is_ok());
+
+        let mut desynced = testing_chunk();
+        desynced.length = 7;
+        let err = desynced.validate().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::LengthMismatch {
+                declared: 7,
+                actual: 42
+            })
+        );
+
+        let mut bad_crc = testing_chunk();
+        bad_crc.crc ^= 1;
+        let err = bad_crc.validate().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PngError>(),
+            Some(PngError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_prepend_and_strip_nonce() {
+        let chunk = testing_chunk();
+        let (nonce, with_nonce) = chunk.prepend_nonce(12);
+        assert_eq!(nonce.len(), 12);
+        assert_eq!(with_nonce.length(), 54);
+        assert_eq!(
+            with_nonce.crc(),
+            crc_for(with_nonce.chunk_type(), with_nonce.data())
+        );
+
+        let (stripped_nonce, data) = with_nonce.strip_nonce(12).unwrap();
+        assert_eq!(stripped_nonce, nonce);
+        assert_eq!(data, chunk.data());
+        assert_ne!(chunk.prepend_nonce(12).0, nonce);
+
+        let err = chunk.strip_nonce(43).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::DataTooShort {
+                len: 42,
+                needed: 43
+            })
+        );
+        assert_eq!(chunk.strip_nonce(0).unwrap(), (&[][..], chunk.data()));
+    }
+
+    #[cfg(feature = "toml-payload")]
+    #[test]
+    fn test_toml_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Settings {
+            name: String,
+            retries: u32,
+            tags: Vec<String>,
+        }
+
+        let settings = Settings {
+            name: "pngme".into(),
+            retries: 3,
+            tags: vec!["a".into(), "b".into()],
+        };
+        let chunk = Chunk::new_from_toml(ChunkType::from_str("ruSt").unwrap(), &settings).unwrap();
+        assert!(chunk.data_as_string().unwrap().contains("retries = 3"));
+        assert_eq!(chunk.data_as_toml::<Settings>().unwrap(), settings);
+
+        let not_toml = Chunk::from_type_and_str("ruSt", "retries = = 3").unwrap();
+        assert!(not_toml.data_as_toml::<Settings>().is_err());
+    }
+}
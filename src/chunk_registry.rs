@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+use crate::chunk_type::ChunkType;
+
+/// Chunk types defined by the PNG specification (including the APNG extension)
+pub const KNOWN_PNG_CHUNKS: [&str; 25] = [
+    // Critical chunks
+    "IHDR", "PLTE", "IDAT", "IEND", // Color space information
+    "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "cICP", "mDCV", "cLLI",
+    // Textual information
+    "tEXt", "zTXt", "iTXt", // Miscellaneous information
+    "bKGD", "hIST", "tRNS", "eXIf", "pHYs", "sPLT", "tIME", // Animation
+    "acTL", "fcTL", "fdAT",
+];
+
+pub fn is_known_chunk_type(chunk_type: &ChunkType) -> bool {
+    KNOWN_PNG_CHUNKS.contains(&chunk_type.to_string().as_str())
+}
@@ -3,17 +3,27 @@ use std::convert::TryFrom;
 use std::str::FromStr;
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::chunk_registry::is_known_chunk_type;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChunkType {
     bytes: [u8; 4]
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = &'static str;    
+    type Error = String;
     
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        if value.iter().any(|&byte| !byte.is_ascii_lowercase() && !byte.is_ascii_uppercase()) {
-            return Err("ChunkType must consist of ASCII letters chars only");
+        if let Some((i, &byte)) = value
+            .iter()
+            .enumerate()
+            .find(|(_, byte)| !byte.is_ascii_alphabetic())
+        {
+            return Err(format!(
+                "ChunkType must consist of ASCII letters only: byte {} ('{}') is not an ASCII letter",
+                i,
+                (byte as char).escape_default()
+            ));
         }
         
         Ok(ChunkType {bytes: value})
@@ -21,16 +31,17 @@ impl TryFrom<[u8; 4]> for ChunkType {
 }
 
 impl FromStr for ChunkType {
-    type Err = &'static str;
+    type Err = String;
 
+    #[allow(clippy::needless_return)]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() != 4 {
-            return Err("Chunk must be jexactly 4 characters long");
+            return Err("Chunk must be jexactly 4 characters long".to_string());
         } 
         else {
             let bytes: [u8; 4] = s.as_bytes()
                 .try_into()
-                .map_err(|_| "Chunk must be exactly 4 characters long")?;
+                .map_err(|_| "Chunk must be exactly 4 characters long".to_string())?;
 
             ChunkType::try_from(bytes)
         }
@@ -49,6 +60,43 @@ impl ChunkType {
     pub fn is_public(&self) -> bool { self.bytes[1].is_ascii_uppercase()}
     pub fn is_reserved_bit_valid(&self) -> bool { self.bytes[2].is_ascii_uppercase() }
     pub fn is_safe_to_copy(&self) -> bool { self.bytes[3].is_ascii_lowercase() }
+    /// Warning text when the reserved bit (case of the third byte) is set,
+    /// which makes the chunk non-conformant to the PNG spec
+    pub fn reserved_bit_warning(&self) -> Option<String> {
+        if self.is_reserved_bit_valid() {
+            return None;
+        }
+        Some(format!(
+            "chunk type '{}' has a lowercase third letter, which violates the PNG spec's reserved bit",
+            self
+        ))
+    }
+
+    /// Returns a copy with the critical bit (case of the first byte) set or cleared
+    pub fn with_critical(&self, critical: bool) -> ChunkType {
+        let mut bytes = self.bytes;
+        bytes[0] = if critical {
+            bytes[0].to_ascii_uppercase()
+        } else {
+            bytes[0].to_ascii_lowercase()
+        };
+        ChunkType { bytes }
+    }
+
+    /// Heuristic, not a PNG spec concept: an ancillary, private type that
+    /// isn't in the known-types registry, as unofficial tools tend to use
+    pub fn is_experimental(&self) -> bool {
+        !self.is_critical() && !self.is_public() && !is_known_chunk_type(self)
+    }
+
+    /// Byte-level match where `None` is a wildcard and `Some(b)` must equal
+    /// the byte at that position exactly
+    pub fn matches_pattern(&self, pattern: [Option<u8>; 4]) -> bool {
+        self.bytes
+            .iter()
+            .zip(pattern.iter())
+            .all(|(byte, expected)| expected.is_none_or(|b| b == *byte))
+    }
     pub fn is_valid(&self) -> bool {
         self.bytes.len() == 4 && 
         self.is_reserved_bit_valid() &&
@@ -141,6 +189,58 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_reserved_bit_warning() {
+        assert!(
+            ChunkType::from_str("ruSt")
+                .unwrap()
+                .reserved_bit_warning()
+                .is_none()
+        );
+
+        let warning = ChunkType::from_str("rust")
+            .unwrap()
+            .reserved_bit_warning()
+            .unwrap();
+        assert!(warning.contains("'rust'"), "{}", warning);
+    }
+
+    #[test]
+    pub fn test_chunk_type_with_critical() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(chunk.with_critical(false).to_string(), "ruSt");
+        assert_eq!(chunk.with_critical(true).to_string(), "RuSt");
+    }
+
+    #[test]
+    pub fn test_chunk_type_error_names_bad_byte() {
+        let err = ChunkType::from_str("ru t").unwrap_err();
+        assert!(
+            err.contains("byte 2 (' ') is not an ASCII letter"),
+            "{}",
+            err
+        );
+
+        let err = ChunkType::try_from([82, 117, 83, 0]).unwrap_err();
+        assert!(err.contains("byte 3 ('\\u{0}')"), "{}", err);
+    }
+
+    #[test]
+    pub fn test_chunk_type_matches_pattern() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert!(chunk_type.matches_pattern([None, Some(b'u'), Some(b'S'), None]));
+        assert!(chunk_type.matches_pattern([None; 4]));
+        assert!(!chunk_type.matches_pattern([Some(b'R'), None, None, None]));
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_experimental() {
+        assert!(ChunkType::from_str("prVt").unwrap().is_experimental());
+        assert!(!ChunkType::from_str("RuSt").unwrap().is_experimental());
+        assert!(!ChunkType::from_str("tEXt").unwrap().is_experimental());
+        assert!(!ChunkType::from_str("pRVt").unwrap().is_experimental());
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
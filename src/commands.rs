@@ -1,4 +1,4 @@
-
+use std::str::FromStr;
 use structopt::StructOpt;
 
 use crate::args::PngMeArgs;
@@ -6,6 +6,31 @@ use crate::args::PngMeArgs;
 #[derive(StructOpt, Debug)]
 #[structopt(name = "pngme", about = "PNGMe CLI Tool for encoding and decoding PNG files")]
 pub struct Cli {
+    /// How to print errors: text or json
+    #[structopt(long = "error-format", default_value = "text")]
+    pub error_format: ErrorFormat,
+
     #[structopt(subcommand)]
     pub command: PngMeArgs,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "Unknown error format '{}', expected text or json",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+#![allow(unused_imports, dead_code)]
+use std::fs;
+use std::path::Path;
+
+use crypto_box::aead::{Aead, AeadCore, OsRng};
+use crypto_box::{PublicKey, SalsaBox, SecretKey};
+
+use crate::Result;
+use crate::chunk::decode_hex;
+use crate::error::PngError;
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 24;
+/// Ephemeral public key followed by the nonce, stored ahead of the ciphertext
+pub const HEADER_LEN: usize = KEY_LEN + NONCE_LEN;
+
+/// Reads an X25519 key from `path`, either as 32 raw bytes or as 64 hex digits
+pub fn read_key_file(path: &Path) -> Result<[u8; KEY_LEN]> {
+    let bytes =
+        fs::read(path).map_err(|e| format!("Failed to read key file {}: {}", path.display(), e))?;
+    let key = match std::str::from_utf8(&bytes) {
+        Ok(text) if text.trim().len() == KEY_LEN * 2 => decode_hex(text.trim())?,
+        _ => bytes,
+    };
+
+    key.as_slice().try_into().map_err(|_| {
+        format!(
+            "Key file {} must hold {} bytes, got {}",
+            path.display(),
+            KEY_LEN,
+            key.len()
+        )
+        .into()
+    })
+}
+
+/// Encrypts `message` so only the holder of the secret key matching
+/// `recipient` can read it. A fresh ephemeral key pair is generated per call
+/// and its public half is stored with the nonce: `epk || nonce || ciphertext`.
+pub fn seal(recipient: &[u8; KEY_LEN], message: &[u8]) -> Result<Vec<u8>> {
+    let ephemeral = SecretKey::generate(&mut OsRng);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = SalsaBox::new(&PublicKey::from(*recipient), &ephemeral)
+        .encrypt(&nonce, message)
+        .map_err(|_| "Failed to encrypt message")?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    sealed.extend_from_slice(ephemeral.public_key().as_bytes());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Reverses [`seal`] with the recipient's secret key
+pub fn open(secret_key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < HEADER_LEN {
+        return Err(PngError::DataTooShort {
+            len: sealed.len(),
+            needed: HEADER_LEN,
+        }
+        .into());
+    }
+    let (ephemeral, rest) = sealed.split_at(KEY_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let ephemeral: [u8; KEY_LEN] = ephemeral.try_into().unwrap();
+
+    SalsaBox::new(&PublicKey::from(ephemeral), &SecretKey::from(*secret_key))
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| "Failed to decrypt message: wrong private key or tampered data".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypair() -> ([u8; KEY_LEN], [u8; KEY_LEN]) {
+        let secret = SecretKey::generate(&mut OsRng);
+        (secret.to_bytes(), *secret.public_key().as_bytes())
+    }
+
+    #[test]
+    fn test_seal_open_round_trip() {
+        let (secret, public) = keypair();
+        let sealed = seal(&public, b"This is where your secret message will be!").unwrap();
+
+        assert_eq!(sealed.len(), HEADER_LEN + 42 + 16);
+        assert_eq!(
+            open(&secret, &sealed).unwrap(),
+            b"This is where your secret message will be!"
+        );
+    }
+
+    #[test]
+    fn test_open_with_wrong_key_fails() {
+        let (_, public) = keypair();
+        let (other_secret, _) = keypair();
+        let sealed = seal(&public, b"hello").unwrap();
+
+        assert!(open(&other_secret, &sealed).is_err());
+        assert!(open(&other_secret, &sealed[..HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn test_read_key_file_hex_and_raw() {
+        let (_, public) = keypair();
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("key_raw");
+        let hex = dir.path().join("key_hex");
+        fs::write(&raw, public).unwrap();
+        fs::write(&hex, format!("{}\n", crate::chunk::encode_hex(&public))).unwrap();
+
+        assert_eq!(read_key_file(&raw).unwrap(), public);
+        assert_eq!(read_key_file(&hex).unwrap(), public);
+    }
+}
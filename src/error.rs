@@ -0,0 +1,202 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PngError {
+    DataTooLarge {
+        len: usize,
+        max: usize,
+    },
+    DataTooShort {
+        len: usize,
+        needed: usize,
+    },
+    CrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    LengthMismatch {
+        declared: u32,
+        actual: usize,
+    },
+    /// The raw type bytes and why `ChunkType` rejected them
+    InvalidChunkType([u8; 4], String),
+    TruncatedData {
+        offset: usize,
+    },
+    InvalidHex(String),
+    InvalidAlignment(usize),
+    UnknownCriticalChunk([u8; 4]),
+    MessageTooLarge {
+        message_bits: u64,
+        max_bits: u64,
+    },
+    Recovered {
+        offset: usize,
+        action: RecoveryAction,
+        cause: Box<PngError>,
+    },
+}
+
+/// What the fallback parser did to get past a recoverable error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryAction {
+    SkipChunk,
+    FixCrc,
+    Truncate,
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PngError::DataTooLarge { len, max } => {
+                write!(
+                    f,
+                    "Chunk data is {} bytes, larger than the allowed {} bytes",
+                    len, max
+                )
+            }
+            PngError::DataTooShort { len, needed } => {
+                write!(f, "Chunk data is {} bytes, need at least {}", len, needed)
+            }
+            PngError::CrcMismatch { expected, actual } => {
+                write!(f, "CRC mismatch: expected {}, got {}", expected, actual)
+            }
+            PngError::LengthMismatch { declared, actual } => {
+                write!(
+                    f,
+                    "Length field says {} bytes but the chunk holds {}",
+                    declared, actual
+                )
+            }
+            PngError::InvalidChunkType(bytes, reason) => {
+                write!(f, "Invalid chunk type {:?}: {}", bytes, reason)
+            }
+            PngError::TruncatedData { offset } => {
+                write!(f, "Incomplete chunk data at offset {}", offset)
+            }
+            PngError::InvalidHex(hex) => {
+                write!(
+                    f,
+                    "Invalid hex string '{}': expected an even number of hex digits",
+                    hex
+                )
+            }
+            PngError::InvalidAlignment(alignment) => {
+                write!(f, "Invalid alignment {}: must be a power of 2", alignment)
+            }
+            PngError::UnknownCriticalChunk(bytes) => {
+                write!(
+                    f,
+                    "Unknown critical chunk type '{}'",
+                    String::from_utf8_lossy(bytes)
+                )
+            }
+            PngError::MessageTooLarge {
+                message_bits,
+                max_bits,
+            } => {
+                write!(
+                    f,
+                    "Message needs {} bits but the image holds at most {} in 4-LSB mode",
+                    message_bits, max_bits
+                )
+            }
+            PngError::Recovered {
+                offset,
+                action,
+                cause,
+            } => {
+                write!(f, "{} (at offset {}), {}", cause, offset, action)
+            }
+        }
+    }
+}
+
+impl PngError {
+    /// Short machine-readable name of the variant, used by `--error-format json`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PngError::DataTooLarge { .. } => "data_too_large",
+            PngError::DataTooShort { .. } => "data_too_short",
+            PngError::CrcMismatch { .. } => "crc_mismatch",
+            PngError::LengthMismatch { .. } => "length_mismatch",
+            PngError::InvalidChunkType(..) => "invalid_chunk_type",
+            PngError::TruncatedData { .. } => "truncated_data",
+            PngError::InvalidHex(_) => "invalid_hex",
+            PngError::InvalidAlignment(_) => "invalid_alignment",
+            PngError::UnknownCriticalChunk(_) => "unknown_critical_chunk",
+            PngError::MessageTooLarge { .. } => "message_too_large",
+            PngError::Recovered { .. } => "recovered",
+        }
+    }
+}
+
+impl fmt::Display for RecoveryAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryAction::SkipChunk => write!(f, "skipped chunk"),
+            RecoveryAction::FixCrc => write!(f, "recomputed CRC"),
+            RecoveryAction::Truncate => write!(f, "dropped remaining bytes"),
+        }
+    }
+}
+
+impl std::error::Error for PngError {}
+
+/// Renders an error as `{"error": "...", "kind": "..."}`. Errors that are not
+/// a `PngError` get the kind `io` or `other`.
+pub fn error_to_json(err: &(dyn std::error::Error + 'static)) -> String {
+    let kind = match err.downcast_ref::<PngError>() {
+        Some(png_error) => png_error.kind(),
+        None if err.is::<std::io::Error>() => "io",
+        None => "other",
+    };
+    format!(
+        "{{\"error\": \"{}\", \"kind\": \"{}\"}}",
+        json_escape(&err.to_string()),
+        kind
+    )
+}
+
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use crate::chunk::Chunk;
+
+    #[test]
+    fn test_crc_mismatch_json() {
+        let bytes = [0, 0, 0, 0, 73, 69, 78, 68, 0, 0, 0, 0];
+        let err = Chunk::try_from(&bytes[..]).unwrap_err();
+
+        assert_eq!(
+            error_to_json(err.as_ref()),
+            r#"{"error": "CRC mismatch: expected 2923585666, got 0", "kind": "crc_mismatch"}"#
+        );
+    }
+
+    #[test]
+    fn test_other_error_json_is_escaped() {
+        let err: Error = "bad \"quote\"\n".into();
+        assert_eq!(
+            error_to_json(err.as_ref()),
+            r#"{"error": "bad \"quote\"\n", "kind": "other"}"#
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::Result;
+
+/// Whether `input` names an `http://` URL rather than a local file
+pub fn is_url(input: &Path) -> bool {
+    input.to_str().is_some_and(|s| s.starts_with("http://"))
+}
+
+/// Downloads `url` with a plain HTTP/1.0 GET. Fails once `timeout` has passed
+/// in total or the body grows past `max_bytes`, whichever comes first.
+pub fn fetch_url(url: &str, timeout: Duration, max_bytes: u64) -> Result<Vec<u8>> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Unsupported URL '{}': only http:// is supported", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = || {
+        format!(
+            "Timed out after {}s fetching {}",
+            timeout.as_secs_f64(),
+            url
+        )
+    };
+    let socket_addr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Could not resolve host '{}'", host))?;
+    let mut stream = TcpStream::connect_timeout(&socket_addr, timeout)
+        .map_err(|e| format!("Failed to connect to {}: {}", authority, e))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    )?;
+
+    // The header is small, so it is read into the same buffer as the body and
+    // split off afterwards; the cap still applies to the whole response
+    let limit = max_bytes.saturating_add(64 * 1024);
+    let mut response = Vec::new();
+    let mut buf = [0u8; 8192];
+    let mut body_start = None;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(timed_out().into());
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let n = match stream.read(&mut buf) {
+            Ok(n) => n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(timed_out().into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+
+        if body_start.is_none() {
+            body_start = response
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .map(|i| i + 4);
+            if let Some(start) = body_start {
+                check_header(&response[..start], url, max_bytes)?;
+            }
+        }
+        let body_len = body_start.map_or(0, |start| response.len() - start);
+        if body_len as u64 > max_bytes || response.len() as u64 > limit {
+            return Err(too_large(url, max_bytes).into());
+        }
+    }
+
+    let start = body_start.ok_or_else(|| format!("Malformed HTTP response from {}", url))?;
+    Ok(response.split_off(start))
+}
+
+/// Rejects non-200 responses and bodies whose declared length is over the cap
+fn check_header(header: &[u8], url: &str, max_bytes: u64) -> Result<()> {
+    let header = String::from_utf8_lossy(header);
+    let mut lines = header.lines();
+    let status = lines.next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("Fetching {} failed: {}", url, status.trim()).into());
+    }
+
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<u64>().ok());
+    if content_length.is_some_and(|len| len > max_bytes) {
+        return Err(too_large(url, max_bytes).into());
+    }
+    Ok(())
+}
+
+fn too_large(url: &str, max_bytes: u64) -> String {
+    format!(
+        "{} is larger than --max-file-size ({} bytes)",
+        url, max_bytes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one connection on a local port with `respond`, returning its URL
+    fn mock_server(respond: impl FnOnce(TcpStream) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            respond(stream);
+        });
+        url
+    }
+
+    #[test]
+    fn test_fetch_url() {
+        let url = mock_server(|mut stream| {
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+                .unwrap();
+        });
+        assert_eq!(
+            fetch_url(&url, Duration::from_secs(5), 1024).unwrap(),
+            b"hello"
+        );
+    }
+
+    #[test]
+    fn test_fetch_url_stalled_response_times_out() {
+        let url = mock_server(|mut stream| {
+            stream.write_all(b"HTTP/1.0 200 OK\r\n").unwrap();
+            thread::sleep(Duration::from_secs(3));
+        });
+        let started = Instant::now();
+        let err = fetch_url(&url, Duration::from_millis(300), 1024).unwrap_err();
+        assert!(err.to_string().starts_with("Timed out"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_fetch_url_oversized_response() {
+        let declared = mock_server(|mut stream| {
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 4096\r\n\r\n")
+                .unwrap();
+            let _ = stream.write_all(&[0u8; 4096]);
+        });
+        let err = fetch_url(&declared, Duration::from_secs(5), 1024).unwrap_err();
+        assert!(err.to_string().contains("--max-file-size"));
+
+        let undeclared = mock_server(|mut stream| {
+            stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n").unwrap();
+            let _ = stream.write_all(&[0u8; 4096]);
+        });
+        let err = fetch_url(&undeclared, Duration::from_secs(5), 1024).unwrap_err();
+        assert!(err.to_string().contains("--max-file-size"));
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("http://example.com/a.png")));
+        assert!(!is_url(Path::new("images/a.png")));
+    }
+}
@@ -1,16 +1,26 @@
-use std::str::FromStr;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use structopt::StructOpt;
 
-use crate::commands::Cli;
-use crate::chunk::Chunk;
+use crate::chunk::{Chunk, deflate, encode_hex, payload_crc};
 use crate::chunk_type::ChunkType;
+use crate::commands::{Cli, ErrorFormat};
 use crate::png::Png;
 
 mod args;
+mod batch;
 mod chunk;
+mod chunk_registry;
 mod chunk_type;
 mod commands;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod error;
+mod fetch;
 mod png;
+#[cfg(any(test, feature = "testing"))]
+mod testutil;
 
 pub type Error = Box<dyn std::error::Error>;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -18,39 +28,234 @@ pub type Result<T> = std::result::Result<T, Error>;
 fn main() -> Result<()> {
     let cli = Cli::from_args();
     
-    match cli.command {
+    match run(cli.command) {
+        Err(e) if cli.error_format == ErrorFormat::Json => {
+            eprintln!("{}", error::error_to_json(e.as_ref()));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+fn run(command: args::PngMeArgs) -> Result<()> {
+    match command {
         args::PngMeArgs::Encode(args) => {
+            args.validate()?;
+
+            let requested_chunk_type = args.requested_chunk_type()?;
+            let tag = args.tag_chunk();
             let input = args.input;
-            let chunk_type = ChunkType::from_str(&args.chunk_type).unwrap(); 
-            let secret_message = args.secret.into_bytes();
-            let mut png = Png::from_file(&input).unwrap();
+            let mut png = match &args.input_base64 {
+                Some(encoded) => Png::from_base64_str(encoded)?,
+                None => Png::from_file(input.as_ref().ok_or("No input PNG given")?)?,
+            };
+            png.set_crc_algorithm(args.crc_algo);
+            if png.is_apng() {
+                eprintln!(
+                    "Warning: input is an animated PNG; animation chunks (acTL/fcTL/fdAT) will be kept as-is"
+                );
+            }
+
+            let chunk_type = match requested_chunk_type {
+                Some(chunk_type) => chunk_type,
+                None => {
+                    let chunk_type = png
+                        .unused_private_type()
+                        .ok_or("No unused private chunk type left")?;
+                    eprintln!("Using generated chunk type '{}'", chunk_type);
+                    chunk_type
+                }
+            };
+            if let Some(warning) = chunk_type.reserved_bit_warning() {
+                eprintln!("Warning: {}", warning);
+            }
+            let chunk_type_name = chunk_type.to_string();
+
+            let mut chunk = match (args.hex_secret, args.secret) {
+                (Some(hex), _) => Chunk::new_from_hex_str(chunk_type, &hex)?,
+                (None, _) if !args.multi_message.is_empty() => {
+                    let messages: Vec<&[u8]> =
+                        args.multi_message.iter().map(|m| m.as_bytes()).collect();
+                    Chunk::from_type_and_bytes(
+                        &chunk_type_name,
+                        &Chunk::encode_length_prefixed(&messages),
+                    )?
+                }
+                (None, secret) => {
+                    let secret = secret
+                        .or(args.pair.map(|(_, message)| message))
+                        .unwrap_or_default();
+                    Chunk::from_type_and_str(&chunk_type_name, &secret)?
+                }
+            };
+            chunk.set_crc_algorithm(args.crc_algo);
+            if args.compress {
+                chunk.set_data(deflate(chunk.data(), args.compress_level)?);
+            }
+            if let Some(pubkey) = &args.recipient_pubkey {
+                chunk.set_data(seal_for_recipient(pubkey, chunk.data())?);
+            }
+            if args.integrity {
+                chunk.set_data(Chunk::add_integrity_header(chunk.data()));
+            }
+            if args.framed {
+                chunk.set_data(Chunk::frame_message(chunk.data()));
+            }
+            if let Some(target_len) = args.pad_to_bytes {
+                chunk.pad_data_to(target_len, 0)?;
+            }
+            if let Some(warning) = chunk.type_collision_warning() {
+                eprintln!("Warning: {}", warning);
+            }
 
-            match args.index {
-                Some(idx) => png.insert_chunk(idx, Chunk::new(chunk_type, secret_message.clone())),
-                None => png.append_chunk(Chunk::new(chunk_type, secret_message.clone()))           
+            match png
+                .chunk_by_type_mut(&chunk_type_name)
+                .filter(|_| args.framed)
+            {
+                Some(existing) => {
+                    let mut data = existing.data().to_vec();
+                    data.extend_from_slice(chunk.data());
+                    existing.set_data(data);
+                }
+                None => {
+                    let idx = match &args.append_before {
+                        Some(before) => png.position_of_type(before).ok_or_else(|| {
+                            format!("Chunk type '{}' not found for --append-before", before)
+                        })?,
+                        None => png.resolve_insert_index(args.index)?,
+                    };
+                    png.insert_chunk(idx, chunk);
+                }
             };
             
-            let output_path = match args.output {
-                Some(path) => path,
-                None => input.with_extension("png")
+            if let Some(tag) = tag {
+                png.append_multiple_chunks_before_iend(vec![tag])?;
+            }
+
+            if args.output_base64 {
+                warn_dropped_trailing_data(&png, args.keep_trailing_data);
+                if args.keep_trailing_data {
+                    println!("{}", png.to_base64_str_with_trailing_data());
+                } else {
+                    println!("{}", png.to_base64_str());
+                }
+                return Ok(());
+            }
+
+            let output_path = match (args.output, input) {
+                (Some(path), _) => path,
+                (None, Some(input)) => input.with_extension("png"),
+                (None, None) => {
+                    return Err("--output is required when reading from --input-base64".into());
+                }
             };
             
-            png.save(&output_path)
-                .map_err(|e| format!("Failed to save PNG file: {}", e))?;
+            save_png(&png, &output_path, args.keep_trailing_data)?;
+            if args.verify_after_write {
+                png.verify_saved(&output_path)?;
+            }
             
             println!("Successfully encoded message into PNG file: {}", output_path.display());
         }
         
         args::PngMeArgs::Decode(args) => {
-            let input = args.input;
-            let chunk_type = args.chunk_type;
+            let chunk_type = args.chunk_type.clone();
             
-            let png = Png::from_file(&input).unwrap();
+            let png = if fetch::is_url(&args.input) {
+                let url = args.input.to_string_lossy();
+                let bytes =
+                    fetch::fetch_url(&url, Duration::from_secs(args.timeout), args.max_file_size)?;
+                Png::from_bytes_with_crc(&bytes, args.crc_algo)?
+            } else {
+                Png::from_file_with_crc(&args.input, args.crc_algo)?
+            };
+            if args.strict {
+                png.check_strict()?;
+            }
+            let chunk_type = match png
+                .find_type_ignore_case(&chunk_type)
+                .filter(|_| args.ignore_case)
+            {
+                Some(found) => resolved_type_name(&chunk_type, found),
+                None => chunk_type,
+            };
             
-            match png.chunk_by_type(&chunk_type) {
+            let chunk = match args.nth {
+                Some(nth) => Some(png.nth_chunk_by_type(&chunk_type, nth)?),
+                None => png.chunk_by_type(&chunk_type),
+            };
+            let decrypted;
+            let chunk = match (chunk, &args.private_key) {
+                (Some(chunk), Some(key)) => {
+                    decrypted = Chunk::new(
+                        chunk.chunk_type().clone(),
+                        open_with_private_key(key, chunk.data())?,
+                    );
+                    Some(&decrypted)
+                }
+                (chunk, _) => chunk,
+            };
+
+            let print_hash = |message: &[u8]| {
+                if args.show_hash {
+                    println!("Message CRC32: {:08x}", payload_crc(message));
+                }
+            };
+
+            match chunk {
+                Some(chunk) if args.framed => {
+                    for (i, message) in chunk.framed_messages()?.iter().enumerate() {
+                        println!(
+                            "Decoded message {}: {}",
+                            i,
+                            String::from_utf8_lossy(message)
+                        );
+                        print_hash(message);
+                    }
+                }
+                Some(chunk) if args.multi_message => {
+                    for (i, message) in Chunk::decode_length_prefixed(chunk.data())?
+                        .iter()
+                        .enumerate()
+                    {
+                        println!(
+                            "Decoded message {}: {}",
+                            i,
+                            String::from_utf8_lossy(message)
+                        );
+                        print_hash(message);
+                    }
+                }
+                Some(chunk) if args.show_lines => {
+                    let lines = chunk.data_as_lines()?;
+                    println!("Decoded message ({} lines):", lines.len());
+                    for (i, line) in lines.iter().enumerate() {
+                        println!("{:>4} | {}", i + 1, line);
+                    }
+                    print_hash(chunk.data());
+                }
+                Some(chunk) if args.size_only => {
+                    let secret_message = args.decoded_payload(chunk)?;
+                    println!("{}", secret_message.len());
+                    print_hash(&secret_message);
+                }
+                Some(chunk) if args.integrity => {
+                    let (_, ok) = chunk.integrity_payload()?;
+                    let secret_message = args.decoded_payload(chunk)?;
+                    println!(
+                        "Decoded message: {}",
+                        String::from_utf8_lossy(&secret_message)
+                    );
+                    println!("Payload integrity {}", if ok { "OK" } else { "FAILED" });
+                    print_hash(&secret_message);
+                }
                 Some(chunk) => {
-                    let secret_message = chunk.data();
-                    println!("Decoded message: {}", String::from_utf8_lossy(secret_message));
+                    let secret_message = args.decoded_payload(chunk)?;
+                    println!(
+                        "Decoded message: {}",
+                        String::from_utf8_lossy(&secret_message)
+                    );
+                    print_hash(&secret_message);
                 }
                 None => {
                     eprintln!("No chunk of type '{}' found in the PNG file.", chunk_type);
@@ -60,10 +265,17 @@ fn main() -> Result<()> {
         
         args::PngMeArgs::Print(args) => {
             let input = args.input;
-            let png = Png::from_file(&input).unwrap();
+            let png = Png::from_file(&input)?;
             
             println!("This is all chunks and their types:");
             for (i, chunk) in png.chunks().iter().enumerate() {
+                if !args.filters.iter().all(|f| f.matches(chunk.chunk_type())) {
+                    continue;
+                }
+                if args.compact {
+                    println!("{}. {}", i, chunk.to_summary_line());
+                    continue;
+                }
                 println!("{}. Chunk Type: {}, Length: {}", i, chunk.chunk_type(), chunk.data().len());
 
             }
@@ -73,17 +285,483 @@ fn main() -> Result<()> {
             let input = args.input;
             let chunk_type = args.chunk_type;
             
-            let mut png = Png::from_file(&input).unwrap();
+            let mut png = Png::from_file(&input)?;
+            if png.is_apng() {
+                eprintln!(
+                    "Warning: input is an animated PNG; animation chunks (acTL/fcTL/fdAT) will be kept as-is"
+                );
+            }
+            let chunk_type = match png
+                .find_type_ignore_case(&chunk_type)
+                .filter(|_| args.ignore_case)
+            {
+                Some(found) => resolved_type_name(&chunk_type, found),
+                None => chunk_type,
+            };
+
+            let savings = png.removal_savings(&chunk_type);
+            if args.dry_run {
+                let savings =
+                    savings.ok_or_else(|| format!("Chunk type '{}' not found", chunk_type))?;
+                println!("Would remove first chunk of type '{}'", chunk_type);
+                if args.show_savings {
+                    println!("Would free {} bytes", savings);
+                }
+                return Ok(());
+            }
             
             png.remove_first_chunk(&chunk_type)
                 .map_err(|e| format!("Failed to remove chunk: {}", e))?;            
             
-            png.save(&input)
-                .map_err(|e| format!("Failed to save PNG file: {}", e))?;
+            save_png(&png, &input, args.keep_trailing_data)?;
+            if args.verify_after_write {
+                png.verify_saved(&input)?;
+            }
             println!("Removed first chunk of type '{}'", chunk_type);
+            if let Some(savings) = savings.filter(|_| args.show_savings) {
+                println!("Freed {} bytes", savings);
+            }
+        }
+
+        args::PngMeArgs::Search(args) => {
+            let needle = args.needle()?;
+            let png = Png::from_file(&args.input)?;
+
+            let hits = png.search_data(&needle);
+            if hits.is_empty() {
+                println!("Pattern not found in any chunk.");
+            }
+            for (i, offset) in hits {
+                println!(
+                    "{}. Chunk Type: {}, Offset: {}",
+                    i,
+                    png.chunks()[i].chunk_type(),
+                    offset
+                );
+            }
+        }
+
+        args::PngMeArgs::Retype(args) => {
+            let mut png = Png::from_file(&args.input)?;
+
+            let chunk = png
+                .chunk_at_mut(args.index)
+                .ok_or_else(|| format!("No chunk at index {}", args.index))?;
+            let old_type = chunk.chunk_type().clone();
+            chunk.set_type(old_type.with_critical(false));
+            let new_type = chunk.chunk_type().clone();
+
+            save_png(&png, &args.input, args.keep_trailing_data)?;
+            println!(
+                "Retyped chunk {} from '{}' to '{}'",
+                args.index, old_type, new_type
+            );
+        }
+
+        args::PngMeArgs::Repair(args) => {
+            let (mut png, warnings) = Png::from_file_with_fallback(&args.input)?;
+            for warning in &warnings {
+                eprintln!("Warning: {}", warning);
+            }
+
+            if args.strip_after_iend {
+                let removed = png.truncate_after_iend();
+                println!("Removed {} chunk(s) after IEND", removed);
+            }
+            for chunk_type in &args.flatten {
+                let removed = png.flatten_type(chunk_type)?;
+                println!(
+                    "Flattened '{}', removed {} extra chunk(s)",
+                    chunk_type, removed
+                );
+            }
+            png.normalize();
+
+            let output_path = args.output.unwrap_or(args.input);
+            save_png(&png, &output_path, args.keep_trailing_data)?;
+            println!("Wrote repaired PNG file: {}", output_path.display());
+        }
+
+        args::PngMeArgs::Sanitize(args) => {
+            let mut png = Png::from_file(&args.input)?;
+            let removed = png.sanitize();
+
+            let output_path = args.output.unwrap_or(args.input);
+            save_png(&png, &output_path, args.keep_trailing_data)?;
+            println!(
+                "Removed {} chunk(s), wrote sanitized PNG file: {}",
+                removed,
+                output_path.display()
+            );
+        }
+
+        args::PngMeArgs::Dump(args) => {
+            let png = Png::from_file(&args.input)?;
+            let dump = png.to_raw_dump();
+
+            match args.output {
+                Some(path) => std::fs::write(&path, dump)
+                    .map_err(|e| format!("Failed to write dump file: {}", e))?,
+                None => print!("{}", dump),
+            }
+        }
+
+        args::PngMeArgs::DumpImage(args) => {
+            let png = Png::from_file(&args.input)?;
+            let stream = png.collect_data_by_type("IDAT");
+
+            std::fs::write(&args.output, &stream)
+                .map_err(|e| format!("Failed to write image stream: {}", e))?;
+            println!(
+                "Wrote {} bytes of IDAT data to {}",
+                stream.len(),
+                args.output.display()
+            );
+        }
+
+        args::PngMeArgs::PixelHash(args) => {
+            let png = Png::from_file(&args.input)?;
+            println!("{}", encode_hex(&png.compute_pixel_hash()?));
+        }
+
+        args::PngMeArgs::Import(args) => {
+            let dump = std::fs::read_to_string(&args.input)?;
+            let png = Png::from_raw_dump(&dump)?;
+
+            save_png(&png, &args.output, true)?;
+            println!(
+                "Imported {} chunk(s) into PNG file: {}",
+                png.chunks().len(),
+                args.output.display()
+            );
+        }
+
+        args::PngMeArgs::Edit(args) => {
+            let mut png = Png::from_file(&args.input)?;
+
+            let data = match args.replacement()? {
+                Some(data) => data,
+                None => {
+                    let current = png
+                        .chunk_by_type(&args.chunk_type)
+                        .ok_or_else(|| format!("Chunk type '{}' not found", args.chunk_type))?;
+                    edit_in_editor(current.data())?
+                }
+            };
+            png.replace_first_chunk_data(&args.chunk_type, data)?;
+
+            let output_path = args.output.unwrap_or(args.input);
+            save_png(&png, &output_path, args.keep_trailing_data)?;
+            if args.verify_after_write {
+                png.verify_saved(&output_path)?;
+            }
+            println!(
+                "Updated chunk '{}' in PNG file: {}",
+                args.chunk_type,
+                output_path.display()
+            );
+        }
+
+        args::PngMeArgs::Find(args) => {
+            let files = png::find_files_with_type(&args.dir, &args.chunk_type, args.recursive)?;
+            for path in &files {
+                println!("{}", path.display());
+            }
+            eprintln!(
+                "{} file(s) contain chunk type '{}'",
+                files.len(),
+                args.chunk_type
+            );
+        }
+
+        args::PngMeArgs::Verify(args) => {
+            let bytes = std::fs::read(&args.input)?;
+            if !png::is_png_bytes(&bytes) {
+                eprintln!("Not a PNG file: missing PNG signature");
+                std::process::exit(2);
+            }
+
+            let png = Png::from_bytes_unverified(&bytes)?;
+            let errors = png.validation_errors();
+            for (index, chunk, e) in &errors {
+                match e.downcast_ref::<error::PngError>() {
+                    Some(error::PngError::CrcMismatch { expected, actual }) => println!(
+                        "Chunk {} ({}): CRC {:08x}, expected {:08x}",
+                        index,
+                        chunk.chunk_type(),
+                        actual,
+                        expected
+                    ),
+                    _ => println!("Chunk {} ({}): {}", index, chunk.chunk_type(), e),
+                }
+            }
+
+            if !errors.is_empty() {
+                std::process::exit(1);
+            }
+            println!("All {} chunks OK", png.chunks().len());
+        }
+
+        args::PngMeArgs::Diff(args) => {
+            let first = Png::from_file(&args.first)?;
+            let second = Png::from_file(&args.second)?;
+
+            let count = first.chunks().len().max(second.chunks().len());
+            let mut differences = 0;
+            for i in 0..count {
+                let difference = match (first.get_chunk_at(i), second.get_chunk_at(i)) {
+                    (Some(a), Some(b)) if a.chunk_type() != b.chunk_type() => {
+                        Some(format!("type {} vs {}", a.chunk_type(), b.chunk_type()))
+                    }
+                    (Some(a), Some(b)) => a.data_mismatch_offset(b).map(|offset| {
+                        format!(
+                            "{} data differs at offset {}: {} vs {}",
+                            a.chunk_type(),
+                            offset,
+                            hex_context(a.data(), offset),
+                            hex_context(b.data(), offset)
+                        )
+                    }),
+                    (Some(a), None) => Some(format!("{} only in first file", a.chunk_type())),
+                    (None, Some(b)) => Some(format!("{} only in second file", b.chunk_type())),
+                    (None, None) => None,
+                };
+
+                if let Some(difference) = difference {
+                    println!("Chunk {}: {}", i, difference);
+                    differences += 1;
+                }
+            }
+            println!("{} chunk(s) differ", differences);
+        }
+
+        args::PngMeArgs::Rewrite(args) => {
+            let output_path = rewrite_file(&args)?;
+            println!("Rewrote PNG file: {}", output_path.display());
+        }
+
+        args::PngMeArgs::Batch(args) => {
+            args.op.validate()?;
+            let on_error = args.on_error();
+            let report = batch::run_batch(args.op.files(), on_error, |path| {
+                let result = args.op.apply(path, args.keep_trailing_data);
+                match &result {
+                    _ if args.summary => {}
+                    Ok(()) => println!("Processed {}", path.display()),
+                    Err(e) if on_error == batch::OnError::Continue => {
+                        eprintln!("Failed {}: {}", path.display(), e)
+                    }
+                    Err(_) => {}
+                }
+                result
+            })?;
+
+            if args.summary {
+                println!("{}", report.summary());
+            }
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+        }
+
+        args::PngMeArgs::Info(args) => {
+            let png = Png::from_file(&args.input)?;
+            if args.strict {
+                png.check_strict()?;
+            }
+
+            println!(
+                "Chunks: {} ({} critical, {} ancillary)",
+                png.chunks().len(),
+                png.count_critical(),
+                png.count_ancillary()
+            );
+            println!("Pixels: {}", png.idat_pixel_count()?);
+            println!(
+                "Chunk data: {} bytes ({:.1}% IDAT)",
+                png.chunk_data_total(),
+                png.chunk_data_ratio_for_type("IDAT") * 100.0
+            );
+            if !png.trailing_data().is_empty() {
+                println!(
+                    "Trailing data: {} bytes after IEND",
+                    png.trailing_data().len()
+                );
+            }
+            if args.capacity {
+                for n in 1..=4 {
+                    println!(
+                        "{}-LSB capacity: {} bytes",
+                        n,
+                        png.idat_byte_capacity_for_lsb_n(n)?
+                    );
+                }
+            }
         }
     }
 
     Ok(())
 }
 
+/// Saves `png` to `path`, warning about and dropping any bytes that followed
+/// `IEND` unless `keep_trailing_data` is set
+fn save_png(png: &Png, path: &Path, keep_trailing_data: bool) -> Result<()> {
+    warn_dropped_trailing_data(png, keep_trailing_data);
+    let saved = if keep_trailing_data {
+        png.save_with_trailing_data(path)
+    } else {
+        png.save(path)
+    };
+    saved.map_err(|e| format!("Failed to save PNG file: {}", e).into())
+}
+
+/// Warns on stderr when writing `png` out is about to lose its trailing data
+fn warn_dropped_trailing_data(png: &Png, keep_trailing_data: bool) {
+    if !png.trailing_data().is_empty() && !keep_trailing_data {
+        eprintln!(
+            "Warning: dropping {} byte(s) after IEND, pass --keep-trailing-data to keep them",
+            png.trailing_data().len()
+        );
+    }
+}
+
+/// Loads `args.input` and writes it back out to `args.output` (or over the
+/// input), returning the path written
+fn rewrite_file(args: &args::RewriteArgs) -> Result<PathBuf> {
+    let bytes = std::fs::read(&args.input)?;
+    let png = if args.recrc {
+        let mut png = Png::from_bytes_unverified(&bytes)?;
+        png.normalize();
+        png
+    } else {
+        Png::try_from(bytes.as_ref())?
+    };
+    if args.strict {
+        png.check_strict()?;
+    }
+
+    let output_path = args.output.clone().unwrap_or_else(|| args.input.clone());
+    let saved = if args.strip_trailing {
+        png.save(&output_path)
+    } else {
+        png.save_with_trailing_data(&output_path)
+    };
+    saved.map_err(|e| format!("Failed to save PNG file: {}", e))?;
+    Ok(output_path)
+}
+
+/// Encrypts `data` to the public key stored in `pubkey_path`
+#[cfg(feature = "crypto")]
+fn seal_for_recipient(pubkey_path: &std::path::Path, data: &[u8]) -> Result<Vec<u8>> {
+    crypto::seal(&crypto::read_key_file(pubkey_path)?, data)
+}
+
+#[cfg(not(feature = "crypto"))]
+fn seal_for_recipient(_pubkey_path: &std::path::Path, _data: &[u8]) -> Result<Vec<u8>> {
+    Err("--recipient-pubkey needs pngme built with the `crypto` feature".into())
+}
+
+/// Decrypts `data` with the private key stored in `key_path`
+#[cfg(feature = "crypto")]
+fn open_with_private_key(key_path: &std::path::Path, data: &[u8]) -> Result<Vec<u8>> {
+    crypto::open(&crypto::read_key_file(key_path)?, data)
+}
+
+#[cfg(not(feature = "crypto"))]
+fn open_with_private_key(_key_path: &std::path::Path, _data: &[u8]) -> Result<Vec<u8>> {
+    Err("--private-key needs pngme built with the `crypto` feature".into())
+}
+
+/// Up to 8 bytes of `data` starting at `offset` as hex, for diff output
+fn hex_context(data: &[u8], offset: usize) -> String {
+    let end = data.len().min(offset + 8);
+    match data.get(offset..end) {
+        Some(bytes) if !bytes.is_empty() => encode_hex(bytes),
+        _ => "(end of data)".to_string(),
+    }
+}
+
+/// Name of the chunk type `--ignore-case` matched, warning when its case
+/// differs from what was asked for
+fn resolved_type_name(requested: &str, found: ChunkType) -> String {
+    let found = found.to_string();
+    if found != requested {
+        eprintln!(
+            "Warning: matched chunk type '{}' for '{}'; letter case carries meaning in PNG chunk types",
+            found, requested
+        );
+    }
+    found
+}
+
+/// Opens `data` in `$EDITOR` through a temp file and returns the saved bytes
+fn edit_in_editor(data: &[u8]) -> Result<Vec<u8>> {
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty() && std::io::stdin().is_terminal())
+        .ok_or("No editor available, pass --secret or --secret-file")?;
+
+    // Created exclusively with owner-only permissions under a random name,
+    // and deleted when `file` drops on every return path
+    let mut file = tempfile::Builder::new()
+        .prefix("pngme-edit-")
+        .suffix(".txt")
+        .tempfile()?;
+    file.write_all(data)?;
+    file.flush()?;
+
+    let mut words = editor.split_whitespace();
+    let status = std::process::Command::new(words.next().unwrap_or_default())
+        .args(words)
+        .arg(file.path())
+        .status();
+    let edited = std::fs::read(file.path());
+
+    if !status?.success() {
+        return Err(format!("Editor '{}' exited with an error", editor).into());
+    }
+    Ok(edited?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{corrupt_crc, make_png};
+
+    fn rewrite_args(input: PathBuf, output: PathBuf, recrc: bool) -> args::RewriteArgs {
+        args::RewriteArgs {
+            input,
+            output: Some(output),
+            strict: false,
+            recrc,
+            strip_trailing: false,
+        }
+    }
+
+    #[test]
+    fn test_rewrite_clean_file_is_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.png");
+        let output = dir.path().join("out.png");
+        let bytes = make_png(&[("IHDR", ""), ("ruSt", "hidden"), ("IEND", "")]).as_bytes();
+        std::fs::write(&input, &bytes).unwrap();
+
+        rewrite_file(&rewrite_args(input, output.clone(), false)).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_rewrite_recrc_fixes_corrupt_crc() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.png");
+        let output = dir.path().join("out.png");
+        let bytes = make_png(&[("IHDR", ""), ("ruSt", "hidden"), ("IEND", "")]).as_bytes();
+        let mut corrupted = bytes.clone();
+        corrupt_crc(&mut corrupted);
+        std::fs::write(&input, &corrupted).unwrap();
+
+        assert!(rewrite_file(&rewrite_args(input.clone(), output.clone(), false)).is_err());
+        rewrite_file(&rewrite_args(input, output.clone(), true)).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), bytes);
+    }
+}
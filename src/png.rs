@@ -1,22 +1,289 @@
 #![allow(unused_imports, dead_code)]
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt;
-use std::path::Path;
 use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use sha2::{Digest, Sha256};
 
-use crate::chunk::Chunk;
 use crate::Result;
+use crate::chunk::{Chunk, CrcAlgorithm, MagicType, decode_hex, deflate, encode_hex, inflate};
+use crate::chunk_type::ChunkType;
+use crate::error::{PngError, RecoveryAction};
+
+/// The 8-byte signature every PNG file starts with
+pub const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Cheap check for whether `data` looks like a PNG, without parsing any chunks
+pub fn is_png_bytes(data: &[u8]) -> bool {
+    data.starts_with(&PNG_SIGNATURE)
+}
+
+/// Sort key for `Png::sort_chunks_by_type_name`: IHDR, then everything else
+/// by type bytes, then IEND
+fn type_name_sort_key(chunk: &Chunk) -> (u8, [u8; 4]) {
+    let bytes = chunk.chunk_type().bytes();
+    match &bytes {
+        b"IHDR" => (0, bytes),
+        b"IEND" => (2, bytes),
+        _ => (1, bytes),
+    }
+}
+
+/// Prefixes each `stride`-byte row with the filter type (0-4) whose output
+/// has the smallest sum of absolute values, the usual adaptive heuristic
+fn filter_scanlines(pixels: &[u8], stride: usize, bpp: usize) -> Vec<u8> {
+    let mut filtered = Vec::with_capacity(pixels.len() + pixels.len() / stride);
+    let zero_row = vec![0u8; stride];
+
+    for (i, row) in pixels.chunks(stride).enumerate() {
+        let prev = if i == 0 {
+            &zero_row[..]
+        } else {
+            &pixels[(i - 1) * stride..i * stride]
+        };
+        let (filter_type, best) = (0..5u8)
+            .map(|filter_type| (filter_type, filter_row(filter_type, row, prev, bpp)))
+            .min_by_key(|(_, out)| {
+                out.iter()
+                    .map(|&b| (b as i8).unsigned_abs() as u32)
+                    .sum::<u32>()
+            })
+            .unwrap();
+        filtered.push(filter_type);
+        filtered.extend(best);
+    }
+    filtered
+}
+
+fn filter_row(filter_type: u8, row: &[u8], prev: &[u8], bpp: usize) -> Vec<u8> {
+    (0..row.len())
+        .map(|x| {
+            let a = if x >= bpp { row[x - bpp] } else { 0 };
+            let c = if x >= bpp { prev[x - bpp] } else { 0 };
+            row[x].wrapping_sub(filter_predictor(filter_type, a, prev[x], c))
+        })
+        .collect()
+}
+
+/// Reverses `filter_scanlines`, returning the raw rows without filter bytes
+fn unfilter_scanlines(filtered: &[u8], stride: usize, bpp: usize) -> Result<Vec<u8>> {
+    if !filtered.len().is_multiple_of(stride + 1) {
+        return Err(format!(
+            "Image data is {} bytes, not a whole number of {}-byte scanlines",
+            filtered.len(),
+            stride + 1
+        )
+        .into());
+    }
+
+    let mut pixels: Vec<u8> = Vec::with_capacity(filtered.len());
+    for (i, line) in filtered.chunks(stride + 1).enumerate() {
+        let filter_type = line[0];
+        if filter_type > 4 {
+            return Err(format!("Unknown filter type {} on scanline {}", filter_type, i).into());
+        }
+        let row_start = i * stride;
+        for x in 0..stride {
+            let a = if x >= bpp {
+                pixels[row_start + x - bpp]
+            } else {
+                0
+            };
+            let b = if i > 0 {
+                pixels[row_start + x - stride]
+            } else {
+                0
+            };
+            let c = if i > 0 && x >= bpp {
+                pixels[row_start + x - stride - bpp]
+            } else {
+                0
+            };
+            pixels.push(line[1 + x].wrapping_add(filter_predictor(filter_type, a, b, c)));
+        }
+    }
+    Ok(pixels)
+}
+
+/// Predicted byte for PNG filter `filter_type` from the left (`a`), above
+/// (`b`) and upper-left (`c`) neighbours
+fn filter_predictor(filter_type: u8, a: u8, b: u8, c: u8) -> u8 {
+    match filter_type {
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => {
+            let p = a as i16 + b as i16 - c as i16;
+            let (pa, pb, pc) = (
+                (p - a as i16).abs(),
+                (p - b as i16).abs(),
+                (p - c as i16).abs(),
+            );
+            if pa <= pb && pa <= pc {
+                a
+            } else if pb <= pc {
+                b
+            } else {
+                c
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Streams through chunk headers looking for `chunk_type`, skipping chunk
+/// data and CRCs unread. Returns `false` for non-PNG input and stops at IEND.
+pub fn scan_for_type<R: Read>(mut reader: R, chunk_type: &str) -> Result<bool> {
+    let mut signature = [0u8; 8];
+    if reader.read_exact(&mut signature).is_err() || !is_png_bytes(&signature) {
+        return Ok(false);
+    }
+
+    let mut header = [0u8; 8];
+    while reader.read_exact(&mut header).is_ok() {
+        let length = u32::from_be_bytes(header[0..4].try_into().unwrap());
+        let found_type = &header[4..8];
+        if found_type == chunk_type.as_bytes() {
+            return Ok(true);
+        }
+        if found_type == b"IEND" {
+            break;
+        }
+        io::copy(
+            &mut reader.by_ref().take(length as u64 + 4),
+            &mut io::sink(),
+        )?;
+    }
+    Ok(false)
+}
+
+/// `.png` files under `dir` that contain a chunk of `chunk_type`, sorted by
+/// path. Symlinks are not followed. Entries, subdirectories and files that
+/// can't be read are reported on stderr and skipped; only failing to list
+/// `dir` itself is an error.
+pub fn find_files_with_type(dir: &Path, chunk_type: &str, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipping unreadable entry in {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        // `DirEntry::file_type` does not follow symlinks
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            if recursive {
+                match find_files_with_type(&path, chunk_type, recursive) {
+                    Ok(nested) => found.extend(nested),
+                    Err(e) => eprintln!("Skipping unreadable directory {}: {}", path.display(), e),
+                }
+            }
+            continue;
+        }
+
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if !is_png {
+            continue;
+        }
+        let scanned = fs::File::open(&path)
+            .map_err(crate::Error::from)
+            .and_then(|file| scan_for_type(io::BufReader::new(file), chunk_type));
+        match scanned {
+            Ok(true) => found.push(path),
+            Ok(false) => {}
+            Err(e) => eprintln!("Skipping unreadable file {}: {}", path.display(), e),
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Ancillary chunk types `Png::sanitize` always keeps because viewers need
+/// them to render the image (color space, transparency, physical size, animation)
+pub const RENDERING_CHUNK_TYPES: [&str; 11] = [
+    "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "bKGD", "tRNS", "pHYs", "acTL", "fcTL", "fdAT",
+];
+
+/// Text and EXIF chunks, the usual carriers of author, comment and GPS data.
+/// `Png::sanitize` always drops them even though they are safe to copy.
+pub const METADATA_CHUNK_TYPES: [&str; 4] = ["tEXt", "iTXt", "zTXt", "eXIf"];
+
+/// Chunk types whose contents depend on the IHDR color type or bit depth,
+/// so they no longer fit once `Png::replace_image_data` changes either
+pub const COLOR_DEPENDENT_CHUNK_TYPES: [&str; 5] = ["PLTE", "tRNS", "sBIT", "bKGD", "hIST"];
+
+/// How a message measures up against the image's 1-LSB capacity, see
+/// `Png::lsb_capacity_check`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LsbCapacityStatus {
+    pub available_bits: u64,
+    pub message_bits: u64,
+    pub can_fit: bool,
+    pub bits_per_pixel_needed: f64,
+}
 
+impl LsbCapacityStatus {
+    /// Fewest low bits per sample (1 to 4) that hold the message
+    pub fn lsb_count_needed(&self) -> u8 {
+        (1..=4u8)
+            .find(|&n| self.message_bits <= self.available_bits * n as u64)
+            .unwrap_or(4)
+    }
+}
 
 pub struct Png {
-    chunks: Vec<Chunk>
+    chunks: Vec<Chunk>,
+    /// Bytes found after `IEND` when parsing, kept aside rather than parsed
+    trailing_data: Vec<u8>,
 }
 
 impl TryFrom<&[u8]> for Png {
     type Error = crate::Error;
 
     fn try_from(value: &[u8]) -> Result<Self> {
-        if value.len() < 8 || &value[0..8] != Png::STANDARD_HEADER {
+        Png::from_bytes_with_crc(value, CrcAlgorithm::default())
+    }
+}
+
+impl Png {
+    /// Parses a PNG whose chunk CRCs were all computed with `crc_algorithm`
+    pub fn from_bytes_with_crc(value: &[u8], crc_algorithm: CrcAlgorithm) -> Result<Self> {
+        Png::parse_with(value, |bytes| {
+            Chunk::try_from_with_crc(bytes, crc_algorithm)
+        })
+    }
+
+    /// Parses a PNG without checking chunk CRCs, keeping the stored values so
+    /// `chunks_with_crc_errors` can report them
+    pub fn from_bytes_unverified(value: &[u8]) -> Result<Self> {
+        Png::parse_with(value, Chunk::try_from_unverified)
+    }
+
+    #[allow(clippy::needless_return)]
+    fn parse_with(value: &[u8], parse_chunk: impl Fn(&[u8]) -> Result<Chunk>) -> Result<Self> {
+        if !is_png_bytes(value) {
             return Err("Invalid PNG header".into());
         }
         else {
@@ -30,266 +297,2201 @@ impl TryFrom<&[u8]> for Png {
                 let chunk_size_total = 4 + 4 + length as usize + 4;
                 let end = offset + chunk_size_total;
 
-                if end > value.len() {
-                    return Err("Incomplete chunk data".into());
-                }
-                
-                let chunk_bytes = &value[offset..end];
-                let chunk = Chunk::try_from(chunk_bytes)?;
-                chunks.push(chunk);
-                
-                offset = end;
-            }
-            
-            Ok(Png {
-                chunks
-            })
-        }
+                if end > value.len() {
+                    return Err("Incomplete chunk data".into());
+                }
+                
+                let chunk_bytes = &value[offset..end];
+                let chunk = parse_chunk(chunk_bytes)?;
+                let is_iend = &chunk.chunk_type().bytes() == b"IEND";
+                chunks.push(chunk);
+                
+                offset = end;
+                if is_iend {
+                    break;
+                }
+            }
+            
+            Ok(Png {
+                chunks,
+                trailing_data: value[offset..].to_vec(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for Png {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PNG with {} chunks", self.chunks.len())?;
+        write!(f, "PNG Chunks:")?;
+        for chunk in &self.chunks {
+            write!(f, "\n{}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Png {
+    pub const STANDARD_HEADER: [u8; 8] = PNG_SIGNATURE;
+    
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Png {
+            chunks,
+            trailing_data: Vec::new(),
+        }
+    }
+
+    /// Like `from_chunks`, but requires `IHDR` first and `IEND` last so the
+    /// result is a structurally valid PNG
+    pub fn from_vec_of_chunks(chunks: Vec<Chunk>) -> Result<Self> {
+        let type_at = |chunk: Option<&Chunk>| chunk.map(|c| c.chunk_type().to_string());
+
+        if type_at(chunks.first()).as_deref() != Some("IHDR") {
+            return Err("The first chunk of a PNG must be IHDR".into());
+        }
+        if type_at(chunks.last()).as_deref() != Some("IEND") {
+            return Err("The last chunk of a PNG must be IEND".into());
+        }
+        Ok(Png::from_chunks(chunks))
+    }
+
+    /// Bytes that followed `IEND` in the parsed file, such as an appended
+    /// thumbnail or signature. `save` drops them, `save_with_trailing_data` keeps them.
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
+    
+    pub fn append_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+    
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) {
+        if index <= self.chunks.len() {
+            self.chunks.insert(index, chunk);
+        } else {
+            self.chunks.push(chunk);
+        } 
+    }
+    
+    /// Resolves where a new chunk would be inserted: `None` means right before
+    /// `IEND` (or the end if there is none), negative indices count from the end.
+    pub fn resolve_insert_index(&self, requested: Option<isize>) -> Result<usize> {
+        let len = self.chunks.len();
+        let index = match requested {
+            None => self
+                .chunks
+                .iter()
+                .position(|c| c.chunk_type().to_string() == "IEND")
+                .unwrap_or(len),
+            Some(i) if i >= 0 => i as usize,
+            Some(i) => len
+                .checked_sub(i.unsigned_abs())
+                .ok_or_else(|| format!("Index {} is out of bounds for {} chunks", i, len))?,
+        };
+
+        if index > len {
+            return Err(format!("Index {} is out of bounds for {} chunks", index, len).into());
+        }
+        Ok(index)
+    }
+
+    /// Inserts all `chunks` right before `IEND` (or at the end if there is
+    /// none). Either every chunk is added or, on error, none is.
+    pub fn append_multiple_chunks_before_iend(&mut self, chunks: Vec<Chunk>) -> Result<()> {
+        if let Some(bad) = chunks
+            .iter()
+            .find(|c| matches!(&c.chunk_type().bytes(), b"IHDR" | b"IEND"))
+        {
+            return Err(format!("Cannot batch-append a '{}' chunk", bad.chunk_type()).into());
+        }
+
+        let iend = self
+            .chunks
+            .iter()
+            .position(|c| c.chunk_type().to_string() == "IEND")
+            .unwrap_or(self.chunks.len());
+
+        let mut new_chunks = Vec::with_capacity(self.chunks.len() + chunks.len());
+        new_chunks.extend_from_slice(&self.chunks[..iend]);
+        new_chunks.extend(chunks);
+        new_chunks.extend_from_slice(&self.chunks[iend..]);
+
+        self.chunks = new_chunks;
+        Ok(())
+    }
+
+    /// Removes the first chunk whose stored CRC is `crc`, telling apart
+    /// chunks that share a type
+    pub fn remove_chunk_by_crc(&mut self, crc: u32) -> Option<Chunk> {
+        let index = self.chunks.iter().position(|c| c.crc() == crc)?;
+        Some(self.chunks.remove(index))
+    }
+
+    pub fn remove_first_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
+        let index = self.chunks.iter()
+            .position(|c| c.chunk_type().to_string() == chunk_type);
+        
+        let index = match index {
+            Some(i) => i,
+            None => return Err(format!("Chunk tpye '{}' not found", chunk_type).into()),
+        };
+        
+        Ok(self.chunks.remove(index))
+    }
+    
+    /// Exchanges the types of two chunks, recomputing both CRCs
+    pub fn swap_chunk_types(&mut self, index_a: usize, index_b: usize) -> Result<()> {
+        let count = self.chunks.len();
+        if index_a >= count || index_b >= count {
+            return Err(format!(
+                "Chunk index out of bounds: {} and {} for {} chunks",
+                index_a, index_b, count
+            )
+            .into());
+        }
+
+        let type_a = self.chunks[index_a].chunk_type().clone();
+        let type_b = self.chunks[index_b].chunk_type().clone();
+        self.chunks[index_a].set_type(type_b);
+        self.chunks[index_b].set_type(type_a);
+        Ok(())
+    }
+
+    /// Sorts chunks by their 4-byte type, keeping `IHDR` first and `IEND`
+    /// last, as a canonical form for comparing PNGs. Spec ordering rules such
+    /// as `PLTE` before `IDAT` are not kept, so the result is for comparison
+    /// rather than display.
+    pub fn sort_chunks_by_type_name(&mut self) {
+        self.chunks.sort_by_key(type_name_sort_key);
+    }
+
+    pub fn is_sorted_by_type_name(&self) -> bool {
+        self.chunks.is_sorted_by_key(type_name_sort_key)
+    }
+
+    /// Exchanges the positions of the chunks at `i` and `j`
+    pub fn swap_chunks(&mut self, i: usize, j: usize) -> Result<()> {
+        let count = self.chunks.len();
+        if i >= count || j >= count {
+            return Err(format!(
+                "Chunk index out of bounds: {} and {} for {} chunks",
+                i, j, count
+            )
+            .into());
+        }
+
+        self.chunks.swap(i, j);
+        Ok(())
+    }
+
+    /// Renames every chunk whose type is a key of `rename_map` to the mapped
+    /// type. All new types are validated before any chunk is touched.
+    /// Returns the number of chunks renamed.
+    pub fn rename_all_chunk_types(
+        &mut self,
+        rename_map: &HashMap<String, String>,
+    ) -> Result<usize> {
+        let mut new_types = HashMap::new();
+        for (old, new) in rename_map {
+            let new_type = ChunkType::from_str(new)
+                .map_err(|e| format!("Invalid chunk type '{}': {}", new, e))?;
+            new_types.insert(old.as_str(), new_type);
+        }
+
+        let mut renamed = 0;
+        for chunk in &mut self.chunks {
+            if let Some(new_type) = new_types.get(chunk.chunk_type().to_string().as_str()) {
+                chunk.set_type(new_type.clone());
+                renamed += 1;
+            }
+        }
+        Ok(renamed)
+    }
+
+    /// Removes every chunk matching `f`, returning them in their original order
+    pub fn remove_where(&mut self, f: impl FnMut(&Chunk) -> bool) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks).into_iter().partition(f);
+        self.chunks = kept;
+        removed
+    }
+
+    /// Removes every chunk whose type is not in `allowed_types`, returning how many were removed
+    pub fn retain_only_types(&mut self, allowed_types: &[&str]) -> usize {
+        self.remove_where(|c| !allowed_types.contains(&c.chunk_type().to_string().as_str()))
+            .len()
+    }
+
+    /// Removes chunks that may carry hidden or identifying data while keeping
+    /// the image renderable. Kept are:
+    /// - public critical chunks (`IHDR`, `PLTE`, `IDAT`, `IEND`)
+    /// - the rendering chunks in `RENDERING_CHUNK_TYPES`
+    /// - other public ancillary chunks that are safe to copy, except the
+    ///   metadata chunks in `METADATA_CHUNK_TYPES`
+    ///
+    /// Everything else (private chunks, unsafe-to-copy ancillary chunks and
+    /// the text/EXIF chunks) is dropped. Returns the number of chunks removed.
+    pub fn sanitize(&mut self) -> usize {
+        self.remove_where(|chunk| {
+            let chunk_type = chunk.chunk_type();
+            let name = chunk_type.to_string();
+            if RENDERING_CHUNK_TYPES.contains(&name.as_str()) {
+                return false;
+            }
+            if METADATA_CHUNK_TYPES.contains(&name.as_str()) {
+                return true;
+            }
+            !(chunk_type.is_public() && (chunk_type.is_critical() || chunk_type.is_safe_to_copy()))
+        })
+        .len()
+    }
+
+    /// Drops every chunk after the first `IEND`, keeping `IEND` itself.
+    /// Returns the number of chunks removed.
+    pub fn truncate_after_iend(&mut self) -> usize {
+        match self
+            .chunks
+            .iter()
+            .position(|c| c.chunk_type().to_string() == "IEND")
+        {
+            Some(i) => self.chunks.drain(i + 1..).count(),
+            None => 0,
+        }
+    }
+
+    /// Merges every chunk of the ancillary `chunk_type` into the first one,
+    /// concatenating their data in order. Returns the number of chunks removed.
+    pub fn flatten_type(&mut self, chunk_type: &str) -> Result<usize> {
+        let parsed = ChunkType::from_str(chunk_type)?;
+        if parsed.is_critical() {
+            return Err(format!("Refusing to flatten critical chunk type '{}'", chunk_type).into());
+        }
+
+        let mut merged: Vec<u8> = Vec::new();
+        let mut first: Option<usize> = None;
+        let mut removed = 0;
+        let mut index = 0;
+        self.chunks.retain(|c| {
+            let keep = if c.chunk_type() != &parsed {
+                true
+            } else {
+                merged.extend_from_slice(c.data());
+                if first.is_none() {
+                    first = Some(index);
+                    true
+                } else {
+                    removed += 1;
+                    false
+                }
+            };
+            index += 1;
+            keep
+        });
+
+        if let Some(i) = first {
+            self.chunks[i].set_data(merged);
+        }
+        Ok(removed)
+    }
+
+    /// Bytes that `remove_first_chunk(chunk_type)` would free, if such a chunk exists
+    pub fn removal_savings(&self, chunk_type: &str) -> Option<u64> {
+        self.chunk_by_type(chunk_type).map(|c| c.size_on_disk_u64())
+    }
+
+    pub fn header(&self) -> &[u8; 8] {
+        &Self::STANDARD_HEADER
+    }
+    
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+    
+    /// Consumes the PNG, returning its chunks without cloning them
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
+        self.chunks.iter()
+            .find(|c| c.chunk_type().to_string() == chunk_type)
+    }
+    
+    /// The stored type of the first chunk whose type equals `chunk_type`
+    /// ignoring ASCII case, for lookups where the user forgot the case bits
+    pub fn find_type_ignore_case(&self, chunk_type: &str) -> Option<ChunkType> {
+        self.chunks
+            .iter()
+            .map(|c| c.chunk_type())
+            .find(|t| t.to_string().eq_ignore_ascii_case(chunk_type))
+            .cloned()
+    }
+
+    /// Chunks whose data starts with the PNG signature, i.e. a whole PNG
+    /// hidden inside a chunk
+    pub fn find_hidden_png_chunks(&self) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|c| c.data_matches_magic(MagicType::Png))
+            .collect()
+    }
+
+    /// Parses the PNG embedded in `chunk`'s data
+    pub fn extract_nested_png_from_chunk(&self, chunk: &Chunk) -> Result<Png> {
+        Png::try_from(chunk.data())
+    }
+
+    /// Index of the first chunk of `chunk_type`
+    pub fn position_of_type(&self, chunk_type: &str) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|c| c.chunk_type().to_string() == chunk_type)
+    }
+
+    pub fn has_chunk_type(&self, chunk_type: &str) -> bool {
+        self.chunks
+            .iter()
+            .any(|c| c.chunk_type().to_string() == chunk_type)
+    }
+
+    pub fn first_chunk(&self) -> Option<&Chunk> {
+        self.chunks.first()
+    }
+    pub fn last_chunk(&self) -> Option<&Chunk> {
+        self.chunks.last()
+    }
+    pub fn first_chunk_of_type(&self, chunk_type: &str) -> Option<&Chunk> {
+        self.chunk_by_type(chunk_type)
+    }
+
+    /// Number of pixels in the image, `width * height` from `IHDR`
+    pub fn idat_pixel_count(&self) -> Result<u64> {
+        let ihdr = self.ihdr_data()?;
+        let width = u32::from_be_bytes(ihdr[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(ihdr[4..8].try_into().unwrap());
+        Ok(width as u64 * height as u64)
+    }
+
+    /// How many bytes fit in the image using the `n` least significant bits of every sample
+    pub fn idat_byte_capacity_for_lsb_n(&self, n: u8) -> Result<u64> {
+        if !(1..=8).contains(&n) {
+            return Err(format!("LSB count must be between 1 and 8, got {}", n).into());
+        }
+
+        Ok(self.idat_pixel_count()? * self.samples_per_pixel()? * n as u64 / 8)
+    }
+
+    /// Checks whether a `message_len`-byte message fits in the lowest bit of
+    /// every sample. Messages that only fit with 2 to 4 low bits per sample
+    /// come back with `can_fit: false`, see `LsbCapacityStatus::lsb_count_needed`;
+    /// anything larger is `PngError::MessageTooLarge`.
+    pub fn lsb_capacity_check(&self, message_len: usize) -> Result<LsbCapacityStatus> {
+        let pixels = self.idat_pixel_count()?;
+        let available_bits = pixels * self.samples_per_pixel()?;
+        let message_bits = message_len as u64 * 8;
+        if message_bits > available_bits * 4 {
+            return Err(PngError::MessageTooLarge {
+                message_bits,
+                max_bits: available_bits * 4,
+            }
+            .into());
+        }
+
+        Ok(LsbCapacityStatus {
+            available_bits,
+            message_bits,
+            can_fit: message_bits <= available_bits,
+            bits_per_pixel_needed: if pixels == 0 {
+                0.0
+            } else {
+                message_bits as f64 / pixels as f64
+            },
+        })
+    }
+
+    fn samples_per_pixel(&self) -> Result<u64> {
+        match self.ihdr_data()?[9] {
+            0 | 3 => Ok(1),
+            4 => Ok(2),
+            2 => Ok(3),
+            6 => Ok(4),
+            color_type => Err(format!("Unknown IHDR color type {}", color_type).into()),
+        }
+    }
+
+    /// SHA-256 of the unfiltered pixel bytes. Only IHDR and IDAT feed into
+    /// it, so two files with the same image but different metadata chunks
+    /// hash the same.
+    pub fn compute_pixel_hash(&self) -> Result<[u8; 32]> {
+        let ihdr = self.ihdr_data()?;
+        let width = u32::from_be_bytes(ihdr[0..4].try_into().unwrap()) as usize;
+        let (bit_depth, interlace) = (ihdr[8] as usize, ihdr[12]);
+        if interlace != 0 {
+            return Err("Pixel hashing of interlaced images is not supported".into());
+        }
+
+        let bits_per_pixel = self.samples_per_pixel()? as usize * bit_depth;
+        let stride = (width * bits_per_pixel).div_ceil(8);
+        let bpp = bits_per_pixel.div_ceil(8);
+
+        let filtered = inflate(&self.collect_data_by_type("IDAT"))?;
+        let pixels = unfilter_scanlines(&filtered, stride, bpp)?;
+
+        let mut hash = Sha256::new();
+        hash.update(&ihdr[0..10]);
+        hash.update(&pixels);
+        Ok(hash.finalize().into())
+    }
+
+    /// Replaces the image with `raw_rgb_pixels` (8-bit RGB, row by row): drops
+    /// every IDAT, rewrites IHDR for the new size, applies adaptive filtering,
+    /// compresses and stores the result as one IDAT where the old ones began.
+    /// If the image wasn't 8-bit RGB before, the chunks tied to its old color
+    /// type (`COLOR_DEPENDENT_CHUNK_TYPES`) are dropped as well.
+    pub fn replace_image_data(
+        &mut self,
+        width: u32,
+        height: u32,
+        raw_rgb_pixels: &[u8],
+    ) -> Result<()> {
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Image dimensions must be non-zero, got {}x{}",
+                width, height
+            )
+            .into());
+        }
+        let stride = width as usize * 3;
+        let expected = stride * height as usize;
+        if raw_rgb_pixels.len() != expected {
+            return Err(format!(
+                "Expected {} bytes of RGB pixels for {}x{}, got {}",
+                expected,
+                width,
+                height,
+                raw_rgb_pixels.len()
+            )
+            .into());
+        }
+        let old_ihdr = self.ihdr_data()?;
+        let (old_bit_depth, old_color_type) = (old_ihdr[8], old_ihdr[9]);
+        if (old_bit_depth, old_color_type) != (8, 2) {
+            self.chunks.retain(|c| {
+                !COLOR_DEPENDENT_CHUNK_TYPES.contains(&c.chunk_type().to_string().as_str())
+            });
+        }
+
+        let compressed = deflate(&filter_scanlines(raw_rgb_pixels, stride, 3), 6)?;
+        let idat = Chunk::new(ChunkType::from_str("IDAT")?, compressed);
+
+        let index = match self
+            .chunks
+            .iter()
+            .position(|c| c.chunk_type().to_string() == "IDAT")
+        {
+            Some(index) => index,
+            None => self.resolve_insert_index(None)?,
+        };
+        self.chunks.retain(|c| c.chunk_type().to_string() != "IDAT");
+        self.chunks.insert(index, idat);
+
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        // 8-bit truecolor, deflate, adaptive filtering, no interlace
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+        self.replace_first_chunk_data("IHDR", ihdr)
+    }
+
+    fn ihdr_data(&self) -> Result<&[u8]> {
+        let ihdr = self.chunk_by_type("IHDR").ok_or("PNG has no IHDR chunk")?;
+        if ihdr.data().len() != 13 {
+            return Err(format!(
+                "IHDR chunk must be 13 bytes long, got {}",
+                ihdr.data().len()
+            )
+            .into());
+        }
+        Ok(ihdr.data())
+    }
+
+    /// Whether the file is an animated PNG, i.e. carries an `acTL` chunk
+    pub fn is_apng(&self) -> bool {
+        self.has_chunk_type("acTL")
+    }
+
+    /// Fails on the first critical chunk the spec doesn't define, which a
+    /// conforming decoder must reject. Unknown ancillary chunks are allowed.
+    pub fn check_strict(&self) -> Result<()> {
+        match self
+            .chunks
+            .iter()
+            .find(|c| c.chunk_type().is_critical() && !c.is_known_type())
+        {
+            Some(chunk) => Err(PngError::UnknownCriticalChunk(chunk.chunk_type().bytes()).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// `(index, chunk, expected_crc)` for every chunk whose stored CRC isn't
+    /// the spec CRC; only possible after `from_bytes_unverified`
+    pub fn chunks_with_crc_errors(&self) -> Vec<(usize, &Chunk, u32)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.verify_crc())
+            .map(|(i, c)| (i, c, Chunk::compute_crc(c.chunk_type(), c.data())))
+            .collect()
+    }
+
+    /// `(index, chunk, error)` for every chunk failing `Chunk::validate`;
+    /// like `chunks_with_crc_errors`, only non-empty after `from_bytes_unverified`
+    pub fn validation_errors(&self) -> Vec<(usize, &Chunk, crate::Error)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.validate().err().map(|e| (i, c, e)))
+            .collect()
+    }
+
+    pub fn has_any_crc_errors(&self) -> bool {
+        self.chunks.iter().any(|c| !c.verify_crc())
+    }
+
+    pub fn count_critical(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|c| c.chunk_type().is_critical())
+            .count()
+    }
+
+    pub fn count_ancillary(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|c| !c.chunk_type().is_critical())
+            .count()
+    }
+
+    /// Distinct chunk types in the order they first appear
+    pub fn chunk_types(&self) -> Vec<ChunkType> {
+        let mut types: Vec<ChunkType> = Vec::new();
+        for chunk in &self.chunks {
+            if !types.contains(chunk.chunk_type()) {
+                types.push(chunk.chunk_type().clone());
+            }
+        }
+        types
+    }
+
+    pub fn chunk_type_set(&self) -> HashSet<ChunkType> {
+        self.chunks.iter().map(|c| c.chunk_type().clone()).collect()
+    }
+
+    /// First valid private, ancillary, safe-to-copy type (e.g. `aaAa`) not yet present
+    pub fn unused_private_type(&self) -> Option<ChunkType> {
+        let present = self.chunk_type_set();
+        (b'a'..=b'z')
+            .flat_map(|b0| (b'a'..=b'z').map(move |b1| (b0, b1)))
+            .flat_map(|(b0, b1)| (b'A'..=b'Z').map(move |b2| (b0, b1, b2)))
+            .flat_map(|(b0, b1, b2)| (b'a'..=b'z').map(move |b3| [b0, b1, b2, b3]))
+            .map(|bytes| ChunkType::try_from(bytes).unwrap())
+            .find(|chunk_type| !present.contains(chunk_type))
+    }
+
+    pub fn get_chunk_at(&self, index: usize) -> Option<&Chunk> {
+        self.chunks.get(index)
+    }
+
+    /// Like indexing a slice, but panics with the chunk count in the message
+    pub fn chunk_at(&self, index: usize) -> &Chunk {
+        self.chunks.get(index).unwrap_or_else(|| {
+            panic!(
+                "PNG chunk index {} out of bounds; chunk count is {}",
+                index,
+                self.chunks.len()
+            )
+        })
+    }
+
+    pub fn chunk_at_mut(&mut self, index: usize) -> Option<&mut Chunk> {
+        self.chunks.get_mut(index)
+    }
+
+    /// The data of every chunk of `chunk_type` concatenated in file order
+    pub fn collect_data_by_type(&self, chunk_type: &str) -> Vec<u8> {
+        self.chunks_by_type(chunk_type)
+            .iter()
+            .flat_map(|c| c.data().iter().copied())
+            .collect()
+    }
+
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|c| c.chunk_type().to_string() == chunk_type)
+            .collect()
+    }
+
+    /// The `nth` (1-based) chunk of the given type
+    pub fn nth_chunk_by_type(&self, chunk_type: &str, nth: usize) -> Result<&Chunk> {
+        let chunks = self.chunks_by_type(chunk_type);
+        nth.checked_sub(1)
+            .and_then(|i| chunks.get(i).copied())
+            .ok_or_else(|| {
+                format!(
+                    "Occurrence {} of chunk type '{}' not found, there are {}",
+                    nth,
+                    chunk_type,
+                    chunks.len()
+                )
+                .into()
+            })
+    }
+
+    pub fn chunk_by_type_mut(&mut self, chunk_type: &str) -> Option<&mut Chunk> {
+        self.chunks
+            .iter_mut()
+            .find(|c| c.chunk_type().to_string() == chunk_type)
+    }
+
+    /// Replaces the data of the first chunk of `chunk_type`, recomputing its CRC
+    pub fn replace_first_chunk_data(&mut self, chunk_type: &str, data: Vec<u8>) -> Result<()> {
+        self.chunk_by_type_mut(chunk_type)
+            .ok_or_else(|| format!("Chunk type '{}' not found", chunk_type))?
+            .set_data(data);
+        Ok(())
+    }
+
+    /// Chunks whose data length is within `min..=max`
+    pub fn chunks_with_data_between(&self, min: u32, max: u32) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|c| (min..=max).contains(&c.length()))
+            .collect()
+    }
+
+    /// Removes and returns the chunks whose data length is within `min..=max`
+    pub fn remove_chunks_with_data_between(&mut self, min: u32, max: u32) -> Vec<Chunk> {
+        self.remove_where(|c| (min..=max).contains(&c.length()))
+    }
+
+    /// Returns `(chunk index, offset in data)` for every occurrence of `needle`
+    pub fn search_data(&self, needle: &[u8]) -> Vec<(usize, usize)> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, chunk)| {
+                chunk
+                    .data()
+                    .windows(needle.len())
+                    .enumerate()
+                    .filter(|(_, window)| *window == needle)
+                    .map(move |(offset, _)| (i, offset))
+            })
+            .collect()
+    }
+
+    /// Size of the serialized file in bytes, signature included
+    pub fn total_encoded_size(&self) -> u64 {
+        PNG_SIGNATURE.len() as u64
+            + self
+                .chunks
+                .iter()
+                .map(|c| c.size_on_disk_u64())
+                .sum::<u64>()
+    }
+
+    /// Sum of every chunk's data length, leaving out chunk framing
+    pub fn chunk_data_total(&self) -> u64 {
+        self.chunks.iter().map(|c| c.length() as u64).sum()
+    }
+
+    pub fn chunk_data_total_for_type(&self, chunk_type: &str) -> u64 {
+        self.chunks_by_type(chunk_type)
+            .iter()
+            .map(|c| c.length() as u64)
+            .sum()
+    }
+
+    /// Share of `chunk_data_total` held by chunks of `chunk_type`, from 0.0
+    /// to 1.0; 0.0 when the file has no chunk data at all
+    pub fn chunk_data_ratio_for_type(&self, chunk_type: &str) -> f64 {
+        match self.chunk_data_total() {
+            0 => 0.0,
+            total => self.chunk_data_total_for_type(chunk_type) as f64 / total as f64,
+        }
+    }
+
+    /// Text dump of every chunk's framing, one block per chunk:
+    ///
+    /// ```text
+    /// length 3
+    /// type RuSt
+    /// data 686579
+    /// crc 1a2b3c4d
+    /// ```
+    ///
+    /// Blocks are separated by a blank line. Bytes after `IEND`, if any, follow
+    /// as a final `trailing <hex>` line. `from_raw_dump` reads it back.
+    pub fn to_raw_dump(&self) -> String {
+        let mut dump = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                format!(
+                    "length {}\ntype {}\ndata {}\ncrc {:08x}\n",
+                    chunk.length(),
+                    chunk.chunk_type(),
+                    encode_hex(chunk.data()),
+                    chunk.crc(),
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        if !self.trailing_data.is_empty() {
+            dump.push_str(&format!("\ntrailing {}\n", encode_hex(&self.trailing_data)));
+        }
+        dump
+    }
+
+    /// Rebuilds a PNG from the output of `to_raw_dump`. Lengths and CRCs are
+    /// checked against the data just like when reading a file. Each `length`
+    /// line starts a new chunk, so blank lines between blocks are optional.
+    pub fn from_raw_dump(dump: &str) -> Result<Self> {
+        let mut blocks: Vec<HashMap<&str, &str>> = Vec::new();
+        let mut trailing_data = Vec::new();
+
+        for line in dump.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "trailing" => trailing_data = decode_hex(value)?,
+                "length" => blocks.push(HashMap::from([(key, value)])),
+                _ => {
+                    let block_index = blocks.len();
+                    let fields = blocks
+                        .last_mut()
+                        .ok_or_else(|| format!("'{}' line before the first 'length' line", key))?;
+                    if fields.insert(key, value).is_some() {
+                        return Err(format!(
+                            "Block {} has more than one '{}' line",
+                            block_index, key
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+        for (block_index, fields) in blocks.iter().enumerate() {
+            let field = |key: &str| {
+                fields.get(key).copied().ok_or_else(|| {
+                    format!("Block {} is missing its '{}' line", block_index + 1, key)
+                })
+            };
+
+            let length: u32 = field("length")?
+                .parse()
+                .map_err(|e| format!("Block {}: invalid length: {}", block_index + 1, e))?;
+            let crc = u32::from_str_radix(field("crc")?, 16)
+                .map_err(|e| format!("Block {}: invalid crc: {}", block_index + 1, e))?;
+
+            let mut bytes = length.to_be_bytes().to_vec();
+            bytes.extend_from_slice(field("type")?.as_bytes());
+            bytes.extend(decode_hex(field("data")?)?);
+            bytes.extend_from_slice(&crc.to_be_bytes());
+
+            chunks.push(Chunk::try_from(bytes.as_ref())?);
+        }
+
+        Ok(Png {
+            chunks,
+            trailing_data,
+        })
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Serializes into `buf`, clearing it first, so callers can reuse one allocation
+    pub fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.extend_from_slice(self.header());
+        buf.extend(self.chunks.iter().flat_map(|chunk| chunk.as_bytes()));
+    }
+
+    pub fn from_base64_str(s: &str) -> Result<Self> {
+        let bytes = BASE64.decode(s.trim())?;
+        Self::try_from(bytes.as_ref())
+    }
+
+    pub fn to_base64_str(&self) -> String {
+        BASE64.encode(self.as_bytes())
+    }
+
+    /// Like `to_base64_str`, but encodes any bytes that followed `IEND` after it
+    pub fn to_base64_str_with_trailing_data(&self) -> String {
+        let mut bytes = self.as_bytes();
+        bytes.extend_from_slice(&self.trailing_data);
+        BASE64.encode(bytes)
+    }
+    
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let png = Self::try_from(bytes.as_ref())?;
+        Ok(png)
+    }
+
+    /// Brings every chunk back to spec form before saving; currently that
+    /// means repairing each CRC
+    pub fn normalize(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.repair_crc();
+        }
+    }
+
+    /// Re-checksums every chunk with `crc_algorithm`
+    pub fn set_crc_algorithm(&mut self, crc_algorithm: CrcAlgorithm) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.set_crc_algorithm(crc_algorithm);
+        }
+    }
+
+    pub fn from_file_with_crc<P: AsRef<Path>>(
+        path: P,
+        crc_algorithm: CrcAlgorithm,
+    ) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes_with_crc(&bytes, crc_algorithm)
+    }
+
+    /// Like `from_file`, but recovers from bad CRCs, invalid chunk types and
+    /// truncated trailing data instead of failing. Each recovery is reported
+    /// as a `PngError::Recovered` warning. Bytes after `IEND` are kept as
+    /// `trailing_data`, as in `from_file`.
+    pub fn from_file_with_fallback<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<PngError>)> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes_with_fallback(&bytes)
+    }
+
+    pub fn from_bytes_with_fallback(value: &[u8]) -> Result<(Self, Vec<PngError>)> {
+        if !is_png_bytes(value) {
+            return Err("Invalid PNG header".into());
+        }
+
+        let mut chunks = Vec::new();
+        let mut warnings = Vec::new();
+        let mut offset: usize = 8;
+
+        while offset < value.len() {
+            let recovered = |action, cause| PngError::Recovered {
+                offset,
+                action,
+                cause: Box::new(cause),
+            };
+
+            if offset + 12 > value.len() {
+                warnings.push(recovered(
+                    RecoveryAction::Truncate,
+                    PngError::TruncatedData { offset },
+                ));
+                break;
+            }
+
+            let length_bytes: [u8; 4] = value[offset..offset + 4].try_into().unwrap();
+            let end = offset + 12 + u32::from_be_bytes(length_bytes) as usize;
+            if end > value.len() {
+                warnings.push(recovered(
+                    RecoveryAction::Truncate,
+                    PngError::TruncatedData { offset },
+                ));
+                break;
+            }
+
+            let chunk_count = chunks.len();
+            match Chunk::try_from(&value[offset..end]) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e) => match e.downcast_ref::<PngError>() {
+                    Some(cause @ PngError::CrcMismatch { .. }) => {
+                        let chunk_bytes = &value[offset..end];
+                        let chunk_type =
+                            ChunkType::try_from(<[u8; 4]>::try_from(&chunk_bytes[4..8]).unwrap())?;
+                        chunks.push(Chunk::new(
+                            chunk_type,
+                            chunk_bytes[8..chunk_bytes.len() - 4].to_vec(),
+                        ));
+                        warnings.push(recovered(RecoveryAction::FixCrc, cause.clone()));
+                    }
+                    Some(cause @ PngError::InvalidChunkType(..)) => {
+                        warnings.push(recovered(RecoveryAction::SkipChunk, cause.clone()));
+                    }
+                    _ => return Err(e),
+                },
+            }
+
+            offset = end;
+            // Whatever follows IEND is trailing data, not chunks to recover
+            if chunks.len() > chunk_count && &chunks[chunk_count].chunk_type().bytes() == b"IEND" {
+                break;
+            }
+        }
+
+        let trailing_data = value[offset..].to_vec();
+        Ok((
+            Png {
+                chunks,
+                trailing_data,
+            },
+            warnings,
+        ))
+    }
+    
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let bytes = self.as_bytes();
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Re-reads `path` and checks that its chunks serialize to exactly this
+    /// PNG's bytes, catching writes that didn't land as intended
+    pub fn verify_saved<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let written = Png::from_bytes_unverified(&fs::read(&path)?)?;
+        if written.as_bytes() != self.as_bytes() {
+            return Err(format!(
+                "Verification failed: {} does not match the PNG that was written",
+                path.as_ref().display()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Like `save`, but writes any bytes that followed `IEND` back after it
+    pub fn save_with_trailing_data<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut bytes = self.as_bytes();
+        bytes.extend_from_slice(&self.trailing_data);
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+/// Writes a PNG one chunk at a time, without holding the whole file in memory
+pub struct PngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PngWriter<W> {
+    /// Starts the stream by writing the PNG signature
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&PNG_SIGNATURE)?;
+        Ok(PngWriter { writer })
+    }
+
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+        self.writer.write_all(&chunk.as_bytes())?;
+        Ok(())
+    }
+
+    /// Flushes and hands back the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::chunk::Chunk;
+    use std::str::FromStr;
+    use std::convert::TryFrom;
+
+    use crate::testutil::{corrupt_crc, make_chunk, make_png};
+
+    fn testing_chunks() -> Vec<Chunk> {
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
+    }
+
+    fn testing_png() -> Png {
+        let chunks = testing_chunks();
+        Png::from_chunks(chunks)
+    }
+
+    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
+        use std::str::FromStr;
+
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let data: Vec<u8> = data.bytes().collect();
+
+        Ok(Chunk::new(chunk_type, data))
+    }
+
+    #[test]
+    fn test_from_chunks() {
+        let chunks = testing_chunks();
+        let png = Png::from_chunks(chunks);
+
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_valid_from_bytes() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(png.is_err());
+    }
+
+    #[test]
+    fn test_invalid_chunk() {
+        let mut chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        #[rustfmt::skip]
+        let mut bad_chunk = vec![
+            0, 0, 0, 5,         // length
+            32, 117, 83, 116,   // Chunk Type (bad)
+            65, 64, 65, 66, 67, // Data
+            1, 2, 3, 4, 5       // CRC (bad)
+        ];
+
+        chunk_bytes.append(&mut bad_chunk);
+
+        let png = Png::try_from(chunk_bytes.as_ref());
+
+        assert!(png.is_err());
+    }
+
+
+    #[test]
+    fn test_list_chunks() {
+        let png = testing_png();
+        let chunks = png.chunks();
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_chunk_by_type() {
+        let png = testing_png();
+        let chunk = png.chunk_by_type("FrSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+
+    }
+
+    #[test]
+    fn test_nth_chunk_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("miDl", "I am the second middle"));
+        png.append_chunk(make_chunk("miDl", "I am the third middle"));
+
+        assert_eq!(png.chunks_by_type("miDl").len(), 3);
+        let chunk = png.nth_chunk_by_type("miDl", 2).unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the second middle");
+
+        assert!(png.nth_chunk_by_type("miDl", 4).is_err());
+        assert!(png.nth_chunk_by_type("miDl", 0).is_err());
+    }
+
+    #[test]
+    fn test_append_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let chunk = png.chunk_by_type("TeSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    fn test_resolve_insert_index() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let len = png.chunks().len();
+
+        assert_eq!(png.resolve_insert_index(None).unwrap(), len - 1);
+        assert_eq!(png.resolve_insert_index(Some(0)).unwrap(), 0);
+        assert_eq!(png.resolve_insert_index(Some(len as isize)).unwrap(), len);
+        assert_eq!(png.resolve_insert_index(Some(-1)).unwrap(), len - 1);
+        assert_eq!(png.resolve_insert_index(Some(-(len as isize))).unwrap(), 0);
+
+        assert!(png.resolve_insert_index(Some(len as isize + 1)).is_err());
+        assert!(png.resolve_insert_index(Some(-(len as isize) - 1)).is_err());
+    }
+
+    #[test]
+    fn test_resolve_insert_index_without_iend() {
+        let png = testing_png();
+        assert_eq!(png.resolve_insert_index(None).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_append_multiple_chunks_before_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let count = png.chunks().len();
+        let chunks = vec![
+            make_chunk("ruSt", "part one"),
+            make_chunk("ruSt", "part two"),
+        ];
+
+        png.append_multiple_chunks_before_iend(chunks).unwrap();
+
+        let chunks = png.chunks();
+        assert_eq!(chunks.len(), count + 2);
+        assert_eq!(chunks[count - 1].data_as_string().unwrap(), "part one");
+        assert_eq!(chunks[count].data_as_string().unwrap(), "part two");
+        assert_eq!(chunks[count + 1].chunk_type().to_string(), "IEND");
+    }
+
+    #[test]
+    fn test_append_multiple_chunks_is_atomic() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let chunks = vec![make_chunk("ruSt", "part one"), make_chunk("IEND", "")];
+
+        assert!(png.append_multiple_chunks_before_iend(chunks).is_err());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_remove_first_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        png.remove_first_chunk("TeSt").unwrap();
+        let chunk = png.chunk_by_type("TeSt");
+        assert!(chunk.is_none());
+    }
+
+    #[test]
+    fn test_idat_pixel_count() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.idat_pixel_count().unwrap(), 50 * 50);
+        assert!(testing_png().idat_pixel_count().is_err());
+    }
+
+    #[test]
+    fn test_idat_byte_capacity_for_lsb_n() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.idat_byte_capacity_for_lsb_n(1).unwrap(), 1250);
+        assert_eq!(png.idat_byte_capacity_for_lsb_n(2).unwrap(), 2500);
+        assert!(png.idat_byte_capacity_for_lsb_n(0).is_err());
+    }
+
+    #[test]
+    fn test_lsb_capacity_check() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        let status = png.lsb_capacity_check(1250).unwrap();
+        assert!(status.can_fit);
+        assert_eq!(
+            (status.available_bits, status.message_bits),
+            (10_000, 10_000)
+        );
+        assert_eq!(status.bits_per_pixel_needed, 4.0);
+        assert_eq!(status.lsb_count_needed(), 1);
+
+        let status = png.lsb_capacity_check(2000).unwrap();
+        assert!(!status.can_fit);
+        assert_eq!(status.lsb_count_needed(), 2);
+        assert_eq!(png.lsb_capacity_check(3751).unwrap().lsb_count_needed(), 4);
+
+        let err = png.lsb_capacity_check(5001).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::MessageTooLarge {
+                message_bits: 40_008,
+                max_bits: 40_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_apng() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(!png.is_apng());
+
+        let actl_data = [0, 0, 0, 2, 0, 0, 0, 0];
+        png.insert_chunk(
+            1,
+            Chunk::new(ChunkType::from_str("acTL").unwrap(), actl_data.to_vec()),
+        );
+        assert!(png.is_apng());
+    }
+
+    #[test]
+    fn test_chunk_types() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("miDl", "Another middle"));
+        png.append_chunk(make_chunk("FrSt", "Another first"));
+
+        let types: Vec<String> = png.chunk_types().iter().map(|t| t.to_string()).collect();
+        assert_eq!(types, vec!["FrSt", "miDl", "LASt"]);
+    }
+
+    #[test]
+    fn test_chunk_type_set() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("miDl", "Another middle"));
+
+        let expected: HashSet<ChunkType> = ["FrSt", "miDl", "LASt"]
+            .iter()
+            .map(|t| ChunkType::from_str(t).unwrap())
+            .collect();
+        assert_eq!(png.chunk_type_set(), expected);
+    }
+
+    #[test]
+    fn test_unused_private_type() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("aaAa", "taken"));
+
+        let chunk_type = png.unused_private_type().unwrap();
+        assert!(chunk_type.is_valid());
+        assert!(!chunk_type.is_critical());
+        assert!(!chunk_type.is_public());
+        assert!(chunk_type.is_safe_to_copy());
+        assert!(png.chunk_by_type(&chunk_type.to_string()).is_none());
+    }
+
+    #[test]
+    fn test_chunks_with_data_between() {
+        let png = testing_png();
+        let types: Vec<String> = png
+            .chunks_with_data_between(18, 19)
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, vec!["miDl", "LASt"]);
+        assert!(png.chunks_with_data_between(21, 100).is_empty());
+    }
+
+    #[test]
+    fn test_remove_chunks_with_data_between() {
+        let mut png = testing_png();
+        let removed = png.remove_chunks_with_data_between(20, 20);
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].chunk_type().to_string(), "FrSt");
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), "miDl");
+    }
+
+    #[test]
+    fn test_search_data() {
+        let png = testing_png();
+        let hits = png.search_data(b"another");
+        assert_eq!(hits, vec![(1, 5)]);
+    }
+
+    #[test]
+    fn test_search_data_no_hits() {
+        let png = testing_png();
+        assert!(png.search_data(b"not here").is_empty());
+        assert!(png.search_data(b"").is_empty());
+    }
+
+    fn testing_png_bytes() -> Vec<u8> {
+        Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(
+                testing_chunks()
+                    .into_iter()
+                    .flat_map(|chunk| chunk.as_bytes()),
+            )
+            .collect()
+    }
+
+    #[test]
+    fn test_is_png_bytes() {
+        assert!(is_png_bytes(&PNG_FILE));
+        assert!(is_png_bytes(&PNG_SIGNATURE));
+        assert!(!is_png_bytes(&PNG_SIGNATURE[..7]));
+        assert!(!is_png_bytes(b"GIF89a and some more"));
+    }
+
+    #[test]
+    fn test_fallback_clean_file() {
+        let (png, warnings) = Png::from_bytes_with_fallback(&testing_png_bytes()).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_fallback_fixes_crc() {
+        let mut bytes = testing_png_bytes();
+        corrupt_crc(&mut bytes);
+
+        let (png, warnings) = Png::from_bytes_with_fallback(&bytes).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(
+            png.chunks()[2].data_as_string().unwrap(),
+            "I am the last chunk"
+        );
+        assert!(matches!(
+            warnings.as_slice(),
+            [PngError::Recovered {
+                action: RecoveryAction::FixCrc,
+                ..
+            }]
+        ));
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_fallback_skips_invalid_type() {
+        let mut bytes = testing_png_bytes();
+        bytes[8 + 4] = b' ';
+
+        let (png, warnings) = Png::from_bytes_with_fallback(&bytes).unwrap();
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), "miDl");
+        assert!(matches!(
+            warnings.as_slice(),
+            [PngError::Recovered {
+                offset: 8,
+                action: RecoveryAction::SkipChunk,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_fallback_truncates_trailing_bytes() {
+        let mut bytes = testing_png_bytes();
+        bytes.extend_from_slice(&[0, 0, 0, 9, 1, 2]);
+
+        let (png, warnings) = Png::from_bytes_with_fallback(&bytes).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert!(matches!(
+            warnings.as_slice(),
+            [PngError::Recovered {
+                action: RecoveryAction::Truncate,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_fallback_stops_at_iend() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"appended thumbnail");
+
+        let (png, warnings) = Png::from_bytes_with_fallback(&bytes).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_data(), b"appended thumbnail");
+    }
+
+    #[test]
+    fn test_swap_chunk_types() {
+        let mut png = testing_png();
+        png.swap_chunk_types(0, 2).unwrap();
+
+        let first = &png.chunks()[0];
+        let last = &png.chunks()[2];
+        assert_eq!(first.chunk_type().to_string(), "LASt");
+        assert_eq!(first.data_as_string().unwrap(), "I am the first chunk");
+        assert_eq!(last.chunk_type().to_string(), "FrSt");
+        assert_eq!(last.data_as_string().unwrap(), "I am the last chunk");
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_swap_chunk_types_out_of_bounds() {
+        let mut png = testing_png();
+        assert!(png.swap_chunk_types(0, 3).is_err());
+    }
+
+    #[test]
+    fn test_rename_all_chunk_types() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("miDl", "I am a second middle"));
+
+        let rename_map = HashMap::from([
+            ("miDl".to_string(), "meHd".to_string()),
+            ("LASt".to_string(), "FrSt".to_string()),
+            ("FrSt".to_string(), "LASt".to_string()),
+        ]);
+        assert_eq!(png.rename_all_chunk_types(&rename_map).unwrap(), 4);
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, vec!["LASt", "meHd", "FrSt", "meHd"]);
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_rename_all_chunk_types_is_atomic() {
+        let mut png = testing_png();
+        let rename_map = HashMap::from([
+            ("miDl".to_string(), "meHd".to_string()),
+            ("FrSt".to_string(), "b4d!".to_string()),
+        ]);
+
+        assert!(png.rename_all_chunk_types(&rename_map).is_err());
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "miDl");
+    }
+
+    #[test]
+    fn test_retain_only_types() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.retain_only_types(&["IHDR", "IDAT", "IEND"]), 4);
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, vec!["IHDR", "IDAT", "IEND"]);
+        assert_eq!(png.retain_only_types(&["IHDR", "IDAT", "IEND"]), 0);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.insert_chunk(1, make_chunk("ruSt", "private secret"));
+        png.insert_chunk(1, make_chunk("tIME", "unsafe to copy"));
+
+        assert_eq!(png.sanitize(), 3);
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, vec!["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "IEND"]);
+    }
+
+    #[test]
+    fn test_sanitize_drops_text_and_exif() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_multiple_chunks_before_iend(vec![
+            make_chunk("tEXt", "Author\0someone"),
+            make_chunk("zTXt", "Comment\0\0x"),
+            make_chunk("iTXt", "GPS\0\0\0\0\0x"),
+            make_chunk("eXIf", "MM"),
+            make_chunk("tESt", "public, safe to copy"),
+        ])
+        .unwrap();
+
+        assert_eq!(png.sanitize(), 5);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "tESt", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_truncate_after_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let chunk_count = png.chunks().len();
+        png.append_chunk(make_chunk("baDd", "stray chunk"));
+        png.append_chunk(make_chunk("IEND", ""));
+
+        assert_eq!(png.truncate_after_iend(), 2);
+        assert_eq!(png.chunks().len(), chunk_count);
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_truncate_after_iend_without_iend() {
+        let mut png = testing_png();
+        assert_eq!(png.truncate_after_iend(), 0);
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_removal_savings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("savings.png");
+        fs::write(&path, PNG_FILE).unwrap();
+        let size_before = fs::metadata(&path).unwrap().len();
+
+        let mut png = Png::from_file(&path).unwrap();
+        let savings = png.removal_savings("RuSt").unwrap();
+        png.remove_first_chunk("RuSt").unwrap();
+        png.save(&path).unwrap();
+
+        assert_eq!(size_before - fs::metadata(&path).unwrap().len(), savings);
+        assert_eq!(png.removal_savings("RuSt"), None);
+    }
+
+    #[test]
+    fn test_png_in_png_matches_magic() {
+        use crate::chunk::MagicType;
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let nested = Chunk::new(ChunkType::from_str("ruSt").unwrap(), png.as_bytes());
+        png.append_chunk(nested);
+
+        let matches: Vec<String> = png
+            .chunks()
+            .iter()
+            .filter(|c| c.data_matches_magic(MagicType::Png))
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(matches, vec!["ruSt"]);
+    }
+
+    #[test]
+    fn test_png_from_image_file() {
+        let png = Png::try_from(&PNG_FILE[..]);
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    fn test_as_bytes() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let actual = png.as_bytes();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_total_encoded_size() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.total_encoded_size(), PNG_FILE.len() as u64);
+    }
+
+    #[test]
+    fn test_write_bytes_reuses_buffer() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut buf = b"leftover bytes from a previous png".to_vec();
+
+        png.write_bytes(&mut buf);
+        assert_eq!(buf, png.as_bytes());
+
+        testing_png().write_bytes(&mut buf);
+        assert_eq!(buf, testing_png().as_bytes());
+    }
+
+    #[test]
+    fn test_empty_chunks_round_trip() {
+        let mut png = testing_png();
+        png.insert_chunk(1, make_chunk("emPt", ""));
+        png.append_chunk(make_chunk("IEND", ""));
+
+        let parsed = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed.chunks().len(), 5);
+        assert_eq!(parsed.chunks()[1].length(), 0);
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let encoded = png.to_base64_str();
+        assert!(encoded.starts_with("iVBORw0KGgo"));
+
+        let decoded = Png::from_base64_str(&encoded).unwrap();
+        assert_eq!(decoded.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_base64_with_trailing_data() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"appended thumbnail");
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(
+            BASE64
+                .decode(png.to_base64_str_with_trailing_data())
+                .unwrap(),
+            bytes
+        );
+        assert_eq!(
+            BASE64.decode(png.to_base64_str()).unwrap(),
+            PNG_FILE.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_invalid_base64() {
+        assert!(Png::from_base64_str("not base64!").is_err());
+        assert!(Png::from_base64_str("R0lGODlh").is_err());
+    }
+
+    #[test]
+    fn test_png_trait_impls() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png: Png = TryFrom::try_from(bytes.as_ref()).unwrap();
+
+        let _png_string = format!("{}", png);
+    }
+
+    #[test]
+    fn test_set_crc_algorithm_round_trip() {
+        let mut png = testing_png();
+        png.set_crc_algorithm(CrcAlgorithm::Bzip2);
+        let bytes = png.as_bytes();
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let parsed = Png::from_bytes_with_crc(&bytes, CrcAlgorithm::Bzip2).unwrap();
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_raw_dump_round_trip() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let dump = png.to_raw_dump();
+        assert!(dump.contains("length 3\ntype RuSt\ndata 686579\n"));
+
+        let imported = Png::from_raw_dump(&dump).unwrap();
+        assert_eq!(imported.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_raw_dump_rejects_bad_crc() {
+        let dump = "length 3\ntype RuSt\ndata 686579\ncrc 00000000\n";
+        assert!(Png::from_raw_dump(dump).is_err());
+        assert!(Png::from_raw_dump("type RuSt\ndata 00\ncrc 00000000\n").is_err());
+    }
+
+    #[test]
+    fn test_raw_dump_round_trip_with_trailing_data() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"appended thumbnail");
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+
+        let dump = png.to_raw_dump();
+        assert!(dump.ends_with(&format!("trailing {}\n", encode_hex(b"appended thumbnail"))));
+        let imported = Png::from_raw_dump(&dump).unwrap();
+        assert_eq!(
+            [imported.as_bytes(), imported.trailing_data().to_vec()].concat(),
+            bytes
+        );
+
+        // Blocks don't depend on exact blank-line separators
+        let squashed =
+            Png::from_raw_dump(&dump.replace("\n\n", "\n").replace('\n', "\r\n")).unwrap();
+        assert_eq!(squashed.chunks().len(), 7);
+        assert!(
+            Png::from_raw_dump("length 3\ntype RuSt\ntype RuSt\ndata 686579\ncrc 00000000\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_count_critical_and_ancillary() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        // IHDR, IDAT, RuSt and IEND
+        assert_eq!(png.count_critical(), 4);
+        assert_eq!(png.count_ancillary(), 3);
+    }
+
+    #[test]
+    fn test_first_and_last_chunk() {
+        let png = testing_png();
+        assert_eq!(png.first_chunk().unwrap().chunk_type().to_string(), "FrSt");
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "LASt");
+        assert_eq!(
+            png.first_chunk_of_type("miDl")
+                .unwrap()
+                .data_as_string()
+                .unwrap(),
+            "I am another chunk"
+        );
+        assert!(png.first_chunk_of_type("IEND").is_none());
+
+        let empty = Png::from_chunks(Vec::new());
+        assert!(empty.first_chunk().is_none());
+        assert!(empty.last_chunk().is_none());
+    }
+
+    #[test]
+    fn test_replace_first_chunk_data() {
+        let mut png = testing_png();
+        png.replace_first_chunk_data("miDl", b"edited".to_vec())
+            .unwrap();
+
+        let chunk = png.chunk_by_type("miDl").unwrap();
+        assert_eq!(chunk.data_as_string().unwrap(), "edited");
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+        assert!(png.replace_first_chunk_data("nOne", Vec::new()).is_err());
     }
-}
 
-impl fmt::Display for Png {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "PNG with {} chunks", self.chunks.len())?;
-        write!(f, "PNG Chunks:")?;
-        for chunk in &self.chunks {
-            write!(f, "\n{}", chunk)?;
+    #[test]
+    fn test_png_writer_matches_as_bytes() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        for chunk in png.chunks() {
+            writer.write_chunk(chunk).unwrap();
         }
+        let bytes = writer.finish().unwrap();
 
-        Ok(())
+        assert_eq!(bytes, PNG_FILE.to_vec());
     }
-}
 
-impl Png {
-    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
-    
-    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
-        Png { chunks }
-    }
-    
-    pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk);
+    #[test]
+    fn test_check_strict() {
+        // PNG_FILE carries a made-up critical `RuSt` chunk
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let err = png.check_strict().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PngError>(),
+            Some(&PngError::UnknownCriticalChunk(*b"RuSt"))
+        );
+
+        let mut png = png;
+        png.remove_first_chunk("RuSt").unwrap();
+        png.append_chunk(make_chunk("ruSt", "hey"));
+        assert!(png.check_strict().is_ok());
     }
-    
-    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) {
-        if index <= self.chunks.len() {
-            self.chunks.insert(index, chunk);
-        } else {
-            self.chunks.push(chunk);
-        } 
+
+    #[test]
+    fn test_normalize_repairs_crcs() {
+        let mut png = testing_png();
+        png.set_crc_algorithm(CrcAlgorithm::Iscsi);
+        assert!(png.chunks().iter().all(|c| !c.verify_crc()));
+
+        png.normalize();
+        assert!(png.chunks().iter().all(|c| c.verify_crc()));
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
     }
-    
-    pub fn remove_first_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
-        let index = self.chunks.iter()
-            .position(|c| c.chunk_type().to_string() == chunk_type);
-        
-        let index = match index {
-            Some(i) => i,
-            None => return Err(format!("Chunk tpye '{}' not found", chunk_type).into()),
-        };
-        
-        Ok(self.chunks.remove(index))
+
+    #[test]
+    fn test_into_chunks() {
+        let chunks = testing_png().into_chunks();
+        let types: Vec<String> = chunks.iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt"]);
     }
-    
-    pub fn header(&self) -> &[u8; 8] {
-        &Self::STANDARD_HEADER
+
+    #[test]
+    fn test_chunk_at() {
+        let png = testing_png();
+        assert_eq!(png.chunk_at(1).chunk_type().to_string(), "miDl");
+        assert!(png.get_chunk_at(3).is_none());
     }
-    
-    pub fn chunks(&self) -> &[Chunk] {
-        &self.chunks
+
+    #[test]
+    #[should_panic(expected = "PNG chunk index 3 out of bounds; chunk count is 3")]
+    fn test_chunk_at_out_of_bounds() {
+        testing_png().chunk_at(3);
     }
-    
-    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        self.chunks.iter()
-            .find(|c| c.chunk_type().to_string() == chunk_type)
+
+    #[test]
+    fn test_scan_for_type() {
+        assert!(scan_for_type(&PNG_FILE[..], "RuSt").unwrap());
+        assert!(scan_for_type(&PNG_FILE[..], "IEND").unwrap());
+        assert!(!scan_for_type(&PNG_FILE[..], "tEXt").unwrap());
+        assert!(!scan_for_type(&b"not a png"[..], "RuSt").unwrap());
     }
-    
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::from(self.header().to_vec());
-        
-        bytes.extend(
-            self.chunks.iter()
-                .flat_map(|chunk| chunk.as_bytes())
+
+    #[test]
+    fn test_find_files_with_type() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+
+        fs::write(dir.join("with.png"), PNG_FILE).unwrap();
+        let mut without = Png::try_from(&PNG_FILE[..]).unwrap();
+        without.remove_first_chunk("RuSt").unwrap();
+        fs::write(dir.join("without.png"), without.as_bytes()).unwrap();
+        fs::write(dir.join("nested").join("deep.png"), PNG_FILE).unwrap();
+        fs::write(dir.join("notes.txt"), PNG_FILE).unwrap();
+
+        let shallow = find_files_with_type(dir, "RuSt", false).unwrap();
+        assert_eq!(shallow, vec![dir.join("with.png")]);
+
+        let deep = find_files_with_type(dir, "RuSt", true).unwrap();
+        assert_eq!(
+            deep,
+            vec![dir.join("nested").join("deep.png"), dir.join("with.png")]
         );
-        
-        bytes
     }
-    
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let bytes = fs::read(path)?;
-        let png = Self::try_from(bytes.as_ref())?;
-        Ok(png)
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_files_with_type_skips_symlinks() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("with.png"), PNG_FILE).unwrap();
+        std::os::unix::fs::symlink(dir.join("with.png"), dir.join("link.png")).unwrap();
+        std::os::unix::fs::symlink(dir, dir.join("loop")).unwrap();
+
+        let found = find_files_with_type(dir, "RuSt", true).unwrap();
+        assert_eq!(found, vec![dir.join("with.png")]);
     }
-    
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let bytes = self.as_bytes();
-        fs::write(path, bytes)?;
-        Ok(())
+
+    #[test]
+    fn test_has_chunk_type() {
+        let png = testing_png();
+        assert!(png.has_chunk_type("miDl"));
+        assert!(!png.has_chunk_type("IHDR"));
     }
-}
 
+    #[test]
+    fn test_swap_chunks() {
+        let mut png = testing_png();
+        png.swap_chunks(0, 2).unwrap();
+        assert_eq!(png.chunk_at(0).chunk_type().to_string(), "LASt");
+        assert_eq!(png.chunk_at(2).chunk_type().to_string(), "FrSt");
+        assert!(png.swap_chunks(0, 3).is_err());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::chunk_type::ChunkType;
-    use crate::chunk::Chunk;
-    use std::str::FromStr;
-    use std::convert::TryFrom;
+    #[test]
+    fn test_trailing_data_after_iend() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"appended thumbnail");
 
-    fn testing_chunks() -> Vec<Chunk> {
-        vec![
-            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
-            chunk_from_strings("miDl", "I am another chunk").unwrap(),
-            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
-        ]
-    }
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_data(), b"appended thumbnail");
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
 
-    fn testing_png() -> Png {
-        let chunks = testing_chunks();
-        Png::from_chunks(chunks)
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trailing_data.png");
+        png.save_with_trailing_data(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
     }
 
-    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
-        use std::str::FromStr;
+    #[test]
+    fn test_chunk_after_iend_is_trailing_data() {
+        let appended = make_chunk("ruSt", "appended secret").as_bytes();
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(&appended);
+        bytes.extend_from_slice(b"appended thumbnail");
 
-        let chunk_type = ChunkType::from_str(chunk_type)?;
-        let data: Vec<u8> = data.bytes().collect();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(
+            png.trailing_data(),
+            [&appended[..], b"appended thumbnail"].concat()
+        );
+        assert!(png.chunk_by_type("ruSt").is_none());
+        // `find` and `decode` agree: neither looks past IEND
+        assert!(!scan_for_type(bytes.as_slice(), "ruSt").unwrap());
+    }
 
-        Ok(Chunk::new(chunk_type, data))
+    #[test]
+    fn test_remove_chunk_by_crc() {
+        let mut png = testing_png();
+        png.append_chunk(make_chunk("miDl", "I am a second middle chunk"));
+        let crc = png.chunk_at(3).crc();
+
+        let removed = png.remove_chunk_by_crc(crc).unwrap();
+        assert_eq!(
+            removed.data_as_string().unwrap(),
+            "I am a second middle chunk"
+        );
+        assert_eq!(png.chunks_by_type("miDl").len(), 1);
+        assert!(png.remove_chunk_by_crc(crc).is_none());
     }
 
     #[test]
-    fn test_from_chunks() {
-        let chunks = testing_chunks();
-        let png = Png::from_chunks(chunks);
+    fn test_flatten_type() {
+        let mut png = Png::from_chunks(vec![
+            make_chunk("ruSt", "one "),
+            make_chunk("FrSt", "between"),
+            make_chunk("ruSt", "two "),
+            make_chunk("ruSt", "three"),
+        ]);
 
-        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.flatten_type("ruSt").unwrap(), 2);
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunk_at(0).data_as_string().unwrap(), "one two three");
+        assert_eq!(png.chunk_at(1).chunk_type().to_string(), "FrSt");
+        assert!(png.flatten_type("FrSt").is_err());
     }
 
     #[test]
-    fn test_valid_from_bytes() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_chunks_with_crc_errors() {
+        let clean = Png::from_bytes_unverified(&PNG_FILE).unwrap();
+        assert!(!clean.has_any_crc_errors());
 
-        let bytes: Vec<u8> = Png::STANDARD_HEADER
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+        let mut bytes = testing_png_bytes();
+        corrupt_crc(&mut bytes);
+        assert!(Png::try_from(bytes.as_ref()).is_err());
 
-        let png = Png::try_from(bytes.as_ref());
+        let png = Png::from_bytes_unverified(&bytes).unwrap();
+        assert!(png.has_any_crc_errors());
+        let errors = png.chunks_with_crc_errors();
+        assert_eq!(errors.len(), 1);
 
-        assert!(png.is_ok());
+        let (index, chunk, expected) = errors[0];
+        assert_eq!(index, 2);
+        assert_eq!(chunk.crc(), expected ^ 0xFF);
+
+        assert!(clean.validation_errors().is_empty());
+        let errors = png.validation_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 2);
+        assert_eq!(
+            errors[0].2.downcast_ref::<PngError>(),
+            Some(&PngError::CrcMismatch {
+                expected,
+                actual: expected ^ 0xFF
+            })
+        );
     }
 
     #[test]
-    fn test_invalid_header() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_verify_saved() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verify_saved.png");
 
-        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+        png.save(&path).unwrap();
+        assert!(png.verify_saved(&path).is_ok());
 
-        let png = Png::try_from(bytes.as_ref());
+        fs::write(&path, testing_png_bytes()).unwrap();
+        assert!(png.verify_saved(&path).is_err());
+    }
 
-        assert!(png.is_err());
+    #[test]
+    fn test_find_type_ignore_case() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(
+            png.find_type_ignore_case("rUst").unwrap().to_string(),
+            "RuSt"
+        );
+        assert_eq!(
+            png.find_type_ignore_case("RuSt").unwrap().to_string(),
+            "RuSt"
+        );
+        assert!(png.find_type_ignore_case("rust2").is_none());
     }
 
     #[test]
-    fn test_invalid_chunk() {
-        let mut chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_from_vec_of_chunks() {
+        let chunks = Png::try_from(&PNG_FILE[..]).unwrap().into_chunks();
+        let png = Png::from_vec_of_chunks(chunks.clone()).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
 
-        #[rustfmt::skip]
-        let mut bad_chunk = vec![
-            0, 0, 0, 5,         // length
-            32, 117, 83, 116,   // Chunk Type (bad)
-            65, 64, 65, 66, 67, // Data
-            1, 2, 3, 4, 5       // CRC (bad)
-        ];
+        assert!(Png::from_vec_of_chunks(chunks[1..].to_vec()).is_err());
+        assert!(Png::from_vec_of_chunks(chunks[..6].to_vec()).is_err());
+        assert!(Png::from_vec_of_chunks(testing_chunks()).is_err());
+        assert!(Png::from_vec_of_chunks(Vec::new()).is_err());
+    }
 
-        chunk_bytes.append(&mut bad_chunk);
+    #[test]
+    fn test_find_hidden_png_chunks() {
+        let mut png = testing_png();
+        assert!(png.find_hidden_png_chunks().is_empty());
 
-        let png = Png::try_from(chunk_bytes.as_ref());
+        let nested = Chunk::new(ChunkType::from_str("ruSt").unwrap(), PNG_FILE.to_vec());
+        png.append_chunk(nested);
 
-        assert!(png.is_err());
+        let hidden = png.find_hidden_png_chunks();
+        assert_eq!(hidden.len(), 1);
+        let extracted = png.extract_nested_png_from_chunk(hidden[0]).unwrap();
+        assert_eq!(extracted.as_bytes(), PNG_FILE.to_vec());
     }
 
+    #[test]
+    fn test_sort_chunks_by_type_name() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(!png.is_sorted_by_type_name());
+
+        png.sort_chunks_by_type_name();
+        assert!(png.is_sorted_by_type_name());
+        let types: Vec<String> = png.chunk_types().iter().map(|t| t.to_string()).collect();
+        assert_eq!(
+            types,
+            ["IHDR", "IDAT", "RuSt", "gAMA", "pHYs", "sRGB", "IEND"]
+        );
+    }
 
     #[test]
-    fn test_list_chunks() {
-        let png = testing_png();
-        let chunks = png.chunks();
-        assert_eq!(chunks.len(), 3);
+    fn test_collect_data_by_type() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let idat = png.chunk_by_type("IDAT").unwrap().data().to_vec();
+        assert_eq!(png.collect_data_by_type("IDAT"), idat);
+
+        let index = png.resolve_insert_index(None).unwrap();
+        png.insert_chunk(index, make_chunk("IDAT", "more"));
+        let mut expected = idat;
+        expected.extend_from_slice(b"more");
+        assert_eq!(png.collect_data_by_type("IDAT"), expected);
+        assert!(png.collect_data_by_type("tEXt").is_empty());
     }
 
     #[test]
-    fn test_chunk_by_type() {
-        let png = testing_png();
-        let chunk = png.chunk_by_type("FrSt").unwrap();
-        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
-        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+    fn test_replace_image_data() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let pixels: Vec<u8> = (0..3 * 4 * 3).map(|i| (i * 7) as u8).collect();
+        png.replace_image_data(4, 3, &pixels).unwrap();
+
+        assert_eq!(png.idat_pixel_count().unwrap(), 12);
+        assert_eq!(png.chunk_by_type("IHDR").unwrap().data()[8..10], [8, 2]);
+        assert_eq!(png.chunks_by_type("IDAT").len(), 1);
+        assert_eq!(png.chunk_at(4).chunk_type().to_string(), "IDAT");
 
+        let filtered = inflate(png.chunk_by_type("IDAT").unwrap().data()).unwrap();
+        assert_eq!(unfilter_scanlines(&filtered, 12, 3).unwrap(), pixels);
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+
+        assert!(png.replace_image_data(4, 3, &pixels[1..]).is_err());
+        assert!(png.replace_image_data(0, 3, &[]).is_err());
     }
 
     #[test]
-    fn test_append_chunk() {
-        let mut png = testing_png();
-        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        let chunk = png.chunk_by_type("TeSt").unwrap();
-        assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
-        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+    fn test_replace_image_data_from_indexed() {
+        // 2x1 image, 8-bit palette (color type 3), one row of palette indices
+        let mut ihdr = 2u32.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&1u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 3, 0, 0, 0]);
+        let chunk = |chunk_type: &str, data: Vec<u8>| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", ihdr),
+            chunk("PLTE", vec![255, 0, 0, 0, 0, 255]),
+            chunk("tRNS", vec![0]),
+            chunk("bKGD", vec![1]),
+            chunk("hIST", vec![0, 1, 0, 1]),
+            chunk("IDAT", deflate(&[0, 0, 1], 6).unwrap()),
+            chunk("IEND", Vec::new()),
+        ]);
+
+        let pixels = [255, 0, 0, 0, 0, 255];
+        png.replace_image_data(2, 1, &pixels).unwrap();
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(png.chunk_by_type("IHDR").unwrap().data()[8..10], [8, 2]);
+        let filtered = inflate(png.chunk_by_type("IDAT").unwrap().data()).unwrap();
+        assert_eq!(unfilter_scanlines(&filtered, 6, 3).unwrap(), pixels);
     }
 
     #[test]
-    fn test_remove_first_chunk() {
-        let mut png = testing_png();
-        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        png.remove_first_chunk("TeSt").unwrap();
-        let chunk = png.chunk_by_type("TeSt");
-        assert!(chunk.is_none());
+    fn test_filter_round_trip_each_type() {
+        let prev = [10, 20, 30, 40, 50, 60];
+        let row = [200, 7, 90, 255, 0, 13];
+        for filter_type in 0..5u8 {
+            let mut filtered = vec![0; 7];
+            filtered.push(filter_type);
+            filtered.extend(filter_row(filter_type, &row, &prev, 3));
+            filtered[1..7].copy_from_slice(&prev);
+
+            let pixels = unfilter_scanlines(&filtered, 6, 3).unwrap();
+            assert_eq!(pixels[6..], row, "filter type {}", filter_type);
+        }
     }
 
     #[test]
-    fn test_png_from_image_file() {
-        let png = Png::try_from(&PNG_FILE[..]);
-        assert!(png.is_ok());
+    fn test_insert_before_position_of_type() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let index = png.position_of_type("IDAT").unwrap();
+        png.insert_chunk(index, make_chunk("ruSt", "before image"));
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types[3..6], ["pHYs", "ruSt", "IDAT"]);
+        assert!(png.position_of_type("tEXt").is_none());
     }
 
     #[test]
-    fn test_as_bytes() {
-        let png = Png::try_from(&PNG_FILE[..]).unwrap();
-        let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.to_vec();
-        assert_eq!(actual, expected);
+    fn test_pixel_hash_ignores_metadata() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let hash = png.compute_pixel_hash().unwrap();
+
+        png.append_multiple_chunks_before_iend(vec![make_chunk("tEXt", "Comment\0hello")])
+            .unwrap();
+        png.remove_first_chunk("gAMA").unwrap();
+        assert_eq!(png.compute_pixel_hash().unwrap(), hash);
+
+        let pixels: Vec<u8> = (0..3 * 4 * 3).map(|i| (i * 7) as u8).collect();
+        png.replace_image_data(4, 3, &pixels).unwrap();
+        assert_ne!(png.compute_pixel_hash().unwrap(), hash);
     }
 
     #[test]
-    fn test_png_trait_impls() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_remove_where_ancillary() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let removed = png.remove_where(|c| !c.chunk_type().is_critical());
 
-        let bytes: Vec<u8> = Png::STANDARD_HEADER
+        let removed_types: Vec<String> =
+            removed.iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(removed_types, ["sRGB", "gAMA", "pHYs"]);
+        let kept_types: Vec<String> = png
+            .chunks()
             .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
+            .map(|c| c.chunk_type().to_string())
             .collect();
+        assert_eq!(kept_types, ["IHDR", "IDAT", "RuSt", "IEND"]);
+        assert!(
+            png.remove_where(|c| !c.chunk_type().is_critical())
+                .is_empty()
+        );
+    }
 
-        let png: Png = TryFrom::try_from(bytes.as_ref()).unwrap();
+    #[test]
+    fn test_chunk_data_totals() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.chunk_data_total(), 13 + 1 + 4 + 9 + 4681 + 3);
+        assert_eq!(png.chunk_data_total_for_type("IDAT"), 4681);
+        assert_eq!(png.chunk_data_total_for_type("tEXt"), 0);
+        assert_eq!(png.chunk_data_ratio_for_type("IDAT"), 4681.0 / 4711.0);
 
-        let _png_string = format!("{}", png);
+        let empty = Png::from_chunks(vec![]);
+        assert_eq!(empty.chunk_data_total(), 0);
+        assert_eq!(empty.chunk_data_ratio_for_type("IDAT"), 0.0);
     }
 
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
@@ -538,4 +2740,4 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
-}
\ No newline at end of file
+}
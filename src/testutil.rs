@@ -0,0 +1,55 @@
+#![allow(unused_imports, dead_code)]
+//! Fixture builders for tests, compiled for this crate's own tests and with
+//! the `testing` feature. pngme is a binary crate with no library target, so
+//! these cannot be exported to downstream crates. They panic on bad input
+//! instead of returning errors.
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// A chunk of `chunk_type` holding the bytes of `data`
+pub fn make_chunk(chunk_type: &str, data: &str) -> Chunk {
+    let chunk_type = ChunkType::from_str(chunk_type)
+        .unwrap_or_else(|e| panic!("bad fixture chunk type '{}': {}", chunk_type, e));
+    Chunk::new(chunk_type, data.as_bytes().to_vec())
+}
+
+/// A PNG made of `(type, data)` chunks, in order
+pub fn make_png(chunks: &[(&str, &str)]) -> Png {
+    Png::from_chunks(chunks.iter().map(|&(t, d)| make_chunk(t, d)).collect())
+}
+
+/// Flips the last byte of serialized chunk or PNG bytes, which is always
+/// part of the final chunk's CRC
+pub fn corrupt_crc(bytes: &mut [u8]) {
+    let last = bytes.last_mut().expect("cannot corrupt an empty buffer");
+    *last ^= 0xFF;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PngError;
+
+    #[test]
+    fn test_make_png() {
+        let png = make_png(&[("IHDR", ""), ("ruSt", "hidden"), ("IEND", "")]);
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.chunk_at(1).data_as_string().unwrap(), "hidden");
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_corrupt_crc() {
+        let mut bytes = make_chunk("ruSt", "hidden").as_bytes();
+        corrupt_crc(&mut bytes);
+
+        let err = Chunk::try_from(bytes.as_ref()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PngError>(),
+            Some(PngError::CrcMismatch { .. })
+        ));
+    }
+}